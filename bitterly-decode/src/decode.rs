@@ -0,0 +1,185 @@
+//! Decodes dump entries using a description and renders the result as text or JSON.
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::description::{Description, Field};
+use crate::dump::DumpEntry;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedRegister {
+    /// None if the address isn't part of the description
+    pub name: Option<String>,
+    pub address: u64,
+    pub value: u128,
+    pub fields: Vec<DecodedField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedField {
+    pub name: String,
+    pub raw: u128,
+    pub value: FieldValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Bit(bool),
+    Unsigned(u128),
+    Signed(i128),
+    /// Enumerated value, None if the raw value has no name
+    Enum(Option<String>),
+    Quantity {
+        value: f64,
+        unit: Option<String>,
+    },
+}
+
+pub fn decode(description: &Description, entries: &[DumpEntry]) -> Vec<DecodedRegister> {
+    entries
+        .iter()
+        .map(
+            |entry| match description.register_by_address(entry.address) {
+                Some(register) => DecodedRegister {
+                    name: Some(register.name.clone()),
+                    address: entry.address,
+                    value: entry.value,
+                    fields: register
+                        .fields
+                        .iter()
+                        .map(|field| decode_field(field, entry.value))
+                        .collect(),
+                },
+                None => DecodedRegister {
+                    name: None,
+                    address: entry.address,
+                    value: entry.value,
+                    fields: Vec::new(),
+                },
+            },
+        )
+        .collect()
+}
+
+fn decode_field(field: &Field, contents: u128) -> DecodedField {
+    let width = field.width();
+    let raw = (contents >> field.lsb) & (u128::MAX >> (128 - width));
+    let signed = {
+        let shift = 128 - width;
+        ((raw << shift) as i128) >> shift
+    };
+
+    let value = if !field.values.is_empty() {
+        FieldValue::Enum(
+            field
+                .values
+                .iter()
+                .find(|v| v.value == raw)
+                .map(|v| v.name.clone()),
+        )
+    } else if let Some(scale) = field.scale {
+        let raw = if field.signed {
+            signed as f64
+        } else {
+            raw as f64
+        };
+        FieldValue::Quantity {
+            value: raw * scale + field.offset,
+            unit: field.unit.clone(),
+        }
+    } else if width == 1 && !field.signed {
+        FieldValue::Bit(raw != 0)
+    } else if field.signed {
+        FieldValue::Signed(signed)
+    } else {
+        FieldValue::Unsigned(raw)
+    };
+
+    DecodedField {
+        name: field.name.clone(),
+        raw,
+        value,
+    }
+}
+
+pub fn render_json(registers: &[DecodedRegister]) -> String {
+    serde_json::to_string_pretty(registers).expect("decoded registers are always serializable")
+}
+
+pub fn render_text(description: &Description, registers: &[DecodedRegister]) -> String {
+    let digits = (description.register_width / 4) as usize;
+    let name_width = registers
+        .iter()
+        .filter_map(|r| r.name.as_ref().map(|n| n.len()))
+        .chain(
+            registers
+                .iter()
+                .flat_map(|r| r.fields.iter().map(|f| f.name.len() + 2)),
+        )
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for register in registers {
+        let name = register.name.as_deref().unwrap_or("?");
+        let _ = writeln!(
+            out,
+            "{:<name_width$} @ 0x{:02X} = 0x{:0digits$X}",
+            name, register.address, register.value
+        );
+        for field in &register.fields {
+            let described = description
+                .register_by_address(register.address)
+                .and_then(|r| r.fields.iter().find(|f| f.name == field.name));
+            let _ = writeln!(
+                out,
+                "  {:<width$} = {}",
+                field.name,
+                format_value(&field.value, field.raw, described),
+                width = name_width.saturating_sub(2)
+            );
+        }
+    }
+    out
+}
+
+/// Decimal places of `x` written in full, such as 9 for 0.000078125
+fn decimals(x: f64) -> usize {
+    x.abs()
+        .to_string()
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len())
+}
+
+fn format_value(value: &FieldValue, raw: u128, field: Option<&Field>) -> String {
+    match value {
+        FieldValue::Bit(bit) => (*bit as u8).to_string(),
+        FieldValue::Unsigned(v) => format!("{} (0x{:X})", v, v),
+        FieldValue::Signed(v) => v.to_string(),
+        FieldValue::Enum(Some(name)) => format!("{} ({})", name, raw),
+        FieldValue::Enum(None) => format!("<unknown> ({})", raw),
+        FieldValue::Quantity { value, unit } => {
+            // As many decimals as the scale and offset have, which hides floating point
+            // noise such as 5.1000000000000005 without losing nA or pF steps
+            let value = match field.and_then(|f| f.scale.map(|scale| (scale, f.offset))) {
+                Some((scale, offset)) => {
+                    let value = format!("{:.*}", decimals(scale).max(decimals(offset)), value);
+                    if value.contains('.') {
+                        value
+                            .trim_end_matches('0')
+                            .trim_end_matches('.')
+                            .to_string()
+                    } else {
+                        value
+                    }
+                }
+                None => value.to_string(),
+            };
+            match unit {
+                Some(unit) => format!("{} {}", value, unit),
+                None => value,
+            }
+        }
+    }
+}
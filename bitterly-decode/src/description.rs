@@ -0,0 +1,129 @@
+//! Register descriptions used to decode a dump. A description mirrors what is
+//! declared with the bitterly macros: a list of registers, each with an address
+//! and a list of fields. Fields are decoded as bits, raw values, enums or
+//! quantized values depending on which keys are present.
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteOrder {
+    Little,
+    /// The default, as for peripheral!
+    #[default]
+    Big,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Description {
+    pub name: String,
+    /// Width of every register in bits
+    #[serde(default = "default_register_width")]
+    pub register_width: u32,
+    /// Byte order of registers in raw binary dumps
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+    pub registers: Vec<Register>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Register {
+    pub name: String,
+    pub address: u64,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub msb: u32,
+    pub lsb: u32,
+    /// Interpret the field as two's complement
+    #[serde(default)]
+    pub signed: bool,
+    /// Quantization of the field, physical = raw * scale + offset
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub offset: f64,
+    pub unit: Option<String>,
+    /// Named values of an enumerated field
+    #[serde(default)]
+    pub values: Vec<EnumValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EnumValue {
+    pub name: String,
+    pub value: u128,
+}
+
+fn default_register_width() -> u32 {
+    8
+}
+
+impl Field {
+    pub fn width(&self) -> u32 {
+        self.msb - self.lsb + 1
+    }
+}
+
+impl Description {
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        let description: Description =
+            serde_json::from_str(text).map_err(|e| Error::Description(e.to_string()))?;
+        description.validate()
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        let description: Description =
+            toml::from_str(text).map_err(|e| Error::Description(e.to_string()))?;
+        description.validate()
+    }
+
+    /// Loads a description, using the file extension to select JSON or TOML
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&text),
+            Some("toml") => Self::from_toml(&text),
+            _ => Err(Error::Description(format!(
+                "{}: expected a .json or .toml description",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn register_by_address(&self, address: u64) -> Option<&Register> {
+        self.registers.iter().find(|r| r.address == address)
+    }
+
+    fn validate(self) -> Result<Self, Error> {
+        if self.register_width == 0
+            || self.register_width > 128
+            || !self.register_width.is_multiple_of(8)
+        {
+            return Err(Error::Description(format!(
+                "register_width must be a multiple of 8 between 8 and 128, not {}",
+                self.register_width
+            )));
+        }
+
+        for register in &self.registers {
+            for field in &register.fields {
+                if field.lsb > field.msb || field.msb >= self.register_width {
+                    return Err(Error::Description(format!(
+                        "{}.{}: invalid bit range [{}:{}]",
+                        register.name, field.name, field.msb, field.lsb
+                    )));
+                }
+            }
+        }
+
+        Ok(self)
+    }
+}
@@ -0,0 +1,173 @@
+//! Parsing of register dumps. Three formats are supported:
+//! - `hex`: whitespace or comma separated hex values for consecutive addresses
+//! - `pairs`: one `address: value` (or `address = value`) pair per line, in hex
+//! - `binary`: raw register contents for consecutive addresses
+//!
+//! Lines starting with `#` or `//` are treated as comments in the text formats.
+use crate::description::{ByteOrder, Description};
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    Hex,
+    Pairs,
+    Binary,
+}
+
+impl DumpFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hex" => Some(DumpFormat::Hex),
+            "pairs" => Some(DumpFormat::Pairs),
+            "bin" | "binary" => Some(DumpFormat::Binary),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of a dump: anything that isn't text is binary, and
+    /// text with `:` or `=` separators is treated as address / value pairs.
+    pub fn detect(data: &[u8]) -> Self {
+        match std::str::from_utf8(data) {
+            Ok(text)
+                if text
+                    .lines()
+                    .map(strip_comment)
+                    .any(|l| l.contains([':', '='])) =>
+            {
+                DumpFormat::Pairs
+            }
+            Ok(_) => DumpFormat::Hex,
+            Err(_) => DumpFormat::Binary,
+        }
+    }
+}
+
+/// A single register value read from a dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DumpEntry {
+    pub address: u64,
+    pub value: u128,
+}
+
+/// Parses a dump into address / value entries. `start` is the address of the
+/// first value for the hex and binary formats.
+pub fn parse(
+    data: &[u8],
+    format: DumpFormat,
+    start: u64,
+    description: &Description,
+) -> Result<Vec<DumpEntry>, Error> {
+    let entries = match format {
+        DumpFormat::Hex => parse_hex(as_text(data)?, start)?,
+        DumpFormat::Pairs => parse_pairs(as_text(data)?)?,
+        DumpFormat::Binary => parse_binary(data, start, description)?,
+    };
+
+    let width = description.register_width;
+    match entries
+        .iter()
+        .find(|e| width < 128 && e.value >> width != 0)
+    {
+        Some(entry) => Err(Error::Dump(format!(
+            "value 0x{:X} at address 0x{:02X} is wider than the {} bit registers",
+            entry.value, entry.address, width
+        ))),
+        None => Ok(entries),
+    }
+}
+
+/// Address of the `offset`th value of a dump starting at `start`
+fn address_at(start: u64, offset: usize) -> Result<u64, Error> {
+    start
+        .checked_add(offset as u64)
+        .ok_or_else(|| Error::Dump(format!("address of value {} is too large", offset + 1)))
+}
+
+fn as_text(data: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(data).map_err(|_| Error::Dump("dump is not valid text".into()))
+}
+
+fn strip_comment(line: &str) -> &str {
+    let line = line.trim();
+    if line.starts_with('#') || line.starts_with("//") {
+        ""
+    } else {
+        line
+    }
+}
+
+fn parse_hex_number(text: &str, line: usize) -> Result<u128, Error> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u128::from_str_radix(digits, 16)
+        .map_err(|_| Error::Dump(format!("line {}: invalid hex value '{}'", line, text)))
+}
+
+fn parse_hex(text: &str, start: u64) -> Result<Vec<DumpEntry>, Error> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        for token in strip_comment(line)
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|t| !t.is_empty())
+        {
+            entries.push(DumpEntry {
+                address: address_at(start, entries.len())?,
+                value: parse_hex_number(token, number + 1)?,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_pairs(text: &str) -> Result<Vec<DumpEntry>, Error> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = strip_comment(line);
+        if line.is_empty() {
+            continue;
+        }
+
+        let (address, value) = line.split_once([':', '=']).ok_or_else(|| {
+            Error::Dump(format!("line {}: expected 'address: value'", number + 1))
+        })?;
+        let address = parse_hex_number(address.trim(), number + 1)?;
+        entries.push(DumpEntry {
+            address: u64::try_from(address)
+                .map_err(|_| Error::Dump(format!("line {}: address too large", number + 1)))?,
+            value: parse_hex_number(value.trim(), number + 1)?,
+        });
+    }
+    Ok(entries)
+}
+
+fn parse_binary(
+    data: &[u8],
+    start: u64,
+    description: &Description,
+) -> Result<Vec<DumpEntry>, Error> {
+    let bytes = (description.register_width / 8) as usize;
+    if !data.len().is_multiple_of(bytes) {
+        return Err(Error::Dump(format!(
+            "binary dump length {} is not a multiple of the {} byte register size",
+            data.len(),
+            bytes
+        )));
+    }
+
+    data.chunks(bytes)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let fold = |acc: u128, b: &u8| (acc << 8) | *b as u128;
+            let value = match description.byte_order {
+                ByteOrder::Big => chunk.iter().fold(0, fold),
+                ByteOrder::Little => chunk.iter().rev().fold(0, fold),
+            };
+            Ok(DumpEntry {
+                address: address_at(start, i)?,
+                value,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,32 @@
+//! # bitterly-decode
+//!
+//! Host side decoding of register dumps, for example hex dumps of MAX17261
+//! registers from returned units. A register description (JSON or TOML) lists the
+//! registers and fields of a device in the same terms as the bitterly macros, and
+//! is used to print every register and field of a dump in text or JSON.
+pub mod decode;
+pub mod description;
+pub mod dump;
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Io(String),
+    Description(String),
+    Dump(String),
+    Usage(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(msg) => write!(f, "{}", msg),
+            Error::Description(msg) => write!(f, "invalid description: {}", msg),
+            Error::Dump(msg) => write!(f, "invalid dump: {}", msg),
+            Error::Usage(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
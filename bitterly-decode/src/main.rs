@@ -0,0 +1,104 @@
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use bitterly_decode::decode::{decode, render_json, render_text};
+use bitterly_decode::description::Description;
+use bitterly_decode::dump::{self, DumpFormat};
+use bitterly_decode::Error;
+
+const USAGE: &str = "\
+usage: bitterly-decode <description.json|toml> <dump|-> [options]
+
+options:
+  --format <hex|pairs|bin>  format of the dump, detected when not given
+  --start <address>         address of the first value in hex and binary dumps (default 0)
+  --json                    print the decoded registers as JSON";
+
+struct Args {
+    description: PathBuf,
+    dump: String,
+    format: Option<DumpFormat>,
+    start: u64,
+    json: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, Error> {
+    let usage = |msg: &str| Error::Usage(format!("{}\n\n{}", msg, USAGE));
+    let mut positional = Vec::new();
+    let mut format = None;
+    let mut start = 0;
+    let mut json = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().ok_or_else(|| usage("--format needs a value"))?;
+                format = Some(
+                    DumpFormat::from_name(&name)
+                        .ok_or_else(|| usage(&format!("unknown format '{}'", name)))?,
+                );
+            }
+            "--start" => {
+                let value = args.next().ok_or_else(|| usage("--start needs a value"))?;
+                let digits = value.trim_start_matches("0x");
+                let radix = if digits.len() != value.len() { 16 } else { 10 };
+                start = u64::from_str_radix(digits, radix)
+                    .map_err(|_| usage(&format!("invalid address '{}'", value)))?;
+            }
+            "--json" => json = true,
+            "-h" | "--help" => return Err(Error::Usage(USAGE.into())),
+            _ => positional.push(arg),
+        }
+    }
+
+    match <[String; 2]>::try_from(positional) {
+        Ok([description, dump]) => Ok(Args {
+            description: description.into(),
+            dump,
+            format,
+            start,
+            json,
+        }),
+        Err(_) => Err(usage("expected a description and a dump")),
+    }
+}
+
+fn run() -> Result<String, Error> {
+    let args = parse_args(std::env::args().skip(1))?;
+    let description = Description::load(&args.description)?;
+
+    let data = if args.dump == "-" {
+        let mut data = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut data)
+            .map_err(|e| Error::Io(format!("stdin: {}", e)))?;
+        data
+    } else {
+        std::fs::read(&args.dump).map_err(|e| Error::Io(format!("{}: {}", args.dump, e)))?
+    };
+
+    let format = args.format.unwrap_or_else(|| DumpFormat::detect(&data));
+    let entries = dump::parse(&data, format, args.start, &description)?;
+    let registers = decode(&description, &entries);
+
+    Ok(if args.json {
+        render_json(&registers)
+    } else {
+        render_text(&description, &registers)
+    })
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(output) => {
+            // Ignore errors such as a closed pipe when piping into `head`
+            let _ = writeln!(std::io::stdout(), "{}", output.trim_end());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
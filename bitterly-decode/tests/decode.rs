@@ -0,0 +1,273 @@
+#[cfg(test)]
+mod tests {
+    use bitterly_decode::decode::{decode, render_json, render_text, FieldValue};
+    use bitterly_decode::description::{ByteOrder, Description};
+    use bitterly_decode::dump::{parse, DumpEntry, DumpFormat};
+
+    const DESCRIPTION: &str = r#"
+        name = "Test"
+        register_width = 16
+
+        [[registers]]
+        name = "Status"
+        address = 0x00
+        fields = [
+            { name = "br", msb = 15, lsb = 15 },
+            { name = "por", msb = 1, lsb = 1 },
+        ]
+
+        [[registers]]
+        name = "TAlrtTh"
+        address = 0x02
+        fields = [
+            { name = "tmax", msb = 15, lsb = 8, signed = true, scale = 1.0, unit = "degC" },
+            { name = "tmin", msb = 7, lsb = 0, signed = true },
+        ]
+
+        [[registers]]
+        name = "VCell"
+        address = 0x09
+        fields = [{ name = "voltage", msb = 15, lsb = 0, scale = 0.000078125, offset = 0.5, unit = "V" }]
+
+        [[registers]]
+        name = "MiscCfg"
+        address = 0x2B
+        fields = [
+            { name = "mr", msb = 9, lsb = 5 },
+            { name = "sacfg", msb = 1, lsb = 0, values = [{ name = "RepSOC", value = 0 }, { name = "AvSOC", value = 1 }] },
+        ]
+    "#;
+
+    fn description() -> Description {
+        Description::from_toml(DESCRIPTION).unwrap()
+    }
+
+    #[test]
+    fn description_test() {
+        let toml = description();
+        assert_eq!(toml.registers.len(), 4);
+        // Big endian unless given, as in peripheral!
+        assert_eq!(toml.byte_order, ByteOrder::Big);
+        assert_eq!(toml.register_by_address(0x09).unwrap().name, "VCell");
+
+        let json = Description::from_json(
+            r#"{
+                "name": "Test",
+                "register_width": 16,
+                "byte_order": "big",
+                "registers": [
+                    { "name": "Status", "address": 0, "fields": [{ "name": "br", "msb": 15, "lsb": 15 }] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(json.byte_order, ByteOrder::Big);
+        assert_eq!(json.registers[0].fields[0].width(), 1);
+
+        // Fields outside of the register are rejected
+        assert!(Description::from_toml(
+            r#"
+            name = "Test"
+            register_width = 8
+            registers = [{ name = "A", address = 0, fields = [{ name = "f", msb = 8, lsb = 0 }] }]
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn dump_format_test() {
+        let description = description();
+
+        let hex = b"# comment\n0002 0x0000 7F80\n";
+        assert_eq!(DumpFormat::detect(hex), DumpFormat::Hex);
+        assert_eq!(
+            parse(hex, DumpFormat::Hex, 0, &description).unwrap(),
+            vec![
+                DumpEntry {
+                    address: 0,
+                    value: 0x0002
+                },
+                DumpEntry {
+                    address: 1,
+                    value: 0x0000
+                },
+                DumpEntry {
+                    address: 2,
+                    value: 0x7F80
+                },
+            ]
+        );
+
+        let pairs = b"0x09: A000\n2B = 0x0021\n";
+        assert_eq!(DumpFormat::detect(pairs), DumpFormat::Pairs);
+        assert_eq!(
+            parse(pairs, DumpFormat::Pairs, 0, &description).unwrap(),
+            vec![
+                DumpEntry {
+                    address: 0x09,
+                    value: 0xA000
+                },
+                DumpEntry {
+                    address: 0x2B,
+                    value: 0x0021
+                },
+            ]
+        );
+
+        let binary = [0x02, 0x80, 0xFF, 0x00];
+        assert_eq!(DumpFormat::detect(&binary), DumpFormat::Binary);
+        assert_eq!(
+            parse(&binary, DumpFormat::Binary, 0x08, &description).unwrap(),
+            vec![
+                DumpEntry {
+                    address: 0x08,
+                    value: 0x0280
+                },
+                DumpEntry {
+                    address: 0x09,
+                    value: 0xFF00
+                },
+            ]
+        );
+
+        let mut little_endian = description.clone();
+        little_endian.byte_order = ByteOrder::Little;
+        assert_eq!(
+            parse(&binary[..2], DumpFormat::Binary, 0, &little_endian).unwrap(),
+            vec![DumpEntry {
+                address: 0,
+                value: 0x8002
+            }]
+        );
+
+        assert!(parse(&binary[..3], DumpFormat::Binary, 0, &description).is_err());
+        assert!(parse(b"00: xyz", DumpFormat::Pairs, 0, &description).is_err());
+        assert!(parse(b"0002 0x", DumpFormat::Hex, 0, &description).is_err());
+
+        // Values wider than the registers and addresses past u64::MAX are rejected
+        assert!(parse(b"0x12345", DumpFormat::Hex, 0, &description).is_err());
+        assert!(parse(b"09: 10000", DumpFormat::Pairs, 0, &description).is_err());
+        assert!(parse(b"0000 0000", DumpFormat::Hex, u64::MAX, &description).is_err());
+        assert!(parse(&binary, DumpFormat::Binary, u64::MAX, &description).is_err());
+        assert_eq!(
+            parse(b"0000", DumpFormat::Hex, u64::MAX, &description).unwrap()[0].address,
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn decode_test() {
+        let description = description();
+        let entries = [
+            DumpEntry {
+                address: 0x00,
+                value: 0x8002,
+            },
+            DumpEntry {
+                address: 0x02,
+                value: 0x7F80,
+            },
+            DumpEntry {
+                address: 0x09,
+                value: 0xA000,
+            },
+            DumpEntry {
+                address: 0x2B,
+                value: 0x0061,
+            },
+            DumpEntry {
+                address: 0x30,
+                value: 0x1234,
+            },
+        ];
+        let registers = decode(&description, &entries);
+
+        assert_eq!(registers[0].name.as_deref(), Some("Status"));
+        assert_eq!(registers[0].fields[0].value, FieldValue::Bit(true));
+        assert_eq!(registers[0].fields[1].value, FieldValue::Bit(true));
+
+        assert_eq!(
+            registers[1].fields[0].value,
+            FieldValue::Quantity {
+                value: 127.0,
+                unit: Some("degC".into())
+            }
+        );
+        assert_eq!(registers[1].fields[1].value, FieldValue::Signed(-128));
+
+        assert_eq!(
+            registers[2].fields[0].value,
+            FieldValue::Quantity {
+                value: 3.7,
+                unit: Some("V".into())
+            }
+        );
+
+        assert_eq!(registers[3].fields[0].value, FieldValue::Unsigned(3));
+        assert_eq!(
+            registers[3].fields[1].value,
+            FieldValue::Enum(Some("AvSOC".into()))
+        );
+
+        // Addresses that aren't described are still reported
+        assert_eq!(registers[4].name, None);
+        assert!(registers[4].fields.is_empty());
+
+        let text = render_text(&description, &registers);
+        assert_eq!(
+            text,
+            "Status    @ 0x00 = 0x8002\n\
+             \x20 br      = 1\n\
+             \x20 por     = 1\n\
+             TAlrtTh   @ 0x02 = 0x7F80\n\
+             \x20 tmax    = 127 degC\n\
+             \x20 tmin    = -128\n\
+             VCell     @ 0x09 = 0xA000\n\
+             \x20 voltage = 3.7 V\n\
+             MiscCfg   @ 0x2B = 0x0061\n\
+             \x20 mr      = 3 (0x3)\n\
+             \x20 sacfg   = AvSOC (1)\n\
+             ?         @ 0x30 = 0x1234\n"
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&render_json(&registers)).unwrap();
+        assert_eq!(json[2]["name"], "VCell");
+        assert_eq!(json[2]["fields"][0]["value"]["value"], 3.7);
+        assert_eq!(json[2]["fields"][0]["value"]["unit"], "V");
+        assert_eq!(json[3]["fields"][1]["value"], "AvSOC");
+        assert_eq!(json[4]["name"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn small_scale_test() {
+        let description = Description::from_toml(
+            r#"
+            name = "Test"
+            register_width = 16
+
+            [[registers]]
+            name = "Leakage"
+            address = 0x00
+            fields = [{ name = "current", msb = 15, lsb = 0, scale = 0.0000000001, unit = "A" }]
+            "#,
+        )
+        .unwrap();
+        let entries = [DumpEntry {
+            address: 0,
+            value: 3,
+        }];
+        let registers = decode(&description, &entries);
+
+        // Steps below 1e-9 aren't rounded away
+        match registers[0].fields[0].value {
+            FieldValue::Quantity { value, .. } => assert!((value - 3e-10).abs() < 1e-20),
+            ref value => panic!("{:?} isn't a quantity", value),
+        }
+        assert_eq!(
+            render_text(&description, &registers),
+            "Leakage   @ 0x00 = 0x0003\n\
+             \x20 current = 0.0000000003 A\n"
+        );
+    }
+}
@@ -1,12 +1,12 @@
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
+#![allow(unused_variables)]
 
 pub fn main() {
     use bitterly::{
         bitfield, bitrange, bitrange_enum_values, bitrange_raw, peripheral, register,
         register_backer,
     };
-    use paste::paste;
 
     // Create a u8 Register
     register_backer!(Register, u8);
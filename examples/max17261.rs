@@ -1,12 +1,14 @@
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
+// The example declares more than it uses
+#![allow(dead_code)]
+#![allow(clippy::enum_variant_names, clippy::excessive_precision)]
 
 pub fn main() {
     use bitterly::{
-        bitfield, bitrange, bitrange_enum_values, bitrange_quantized, bitrange_raw, peripheral,
-        register, register_backer,
+        bitfield, bitrange, bitrange_enum_values, bitrange_multi_quantized, bitrange_quantized,
+        bitrange_raw, bitrange_table, peripheral, register, register_array, register_backer,
     };
-    use paste::paste;
 
     const RSENSE: f32 = 0.01; // 10mOhm RSense resistor
 
@@ -18,11 +20,11 @@ pub fn main() {
     const PERCENT_MIN: f32 = 0.0;
     const PERCENT_MAX: f32 = PERCENT_QUANT * 65535.0;
 
-    const VOLTAGE_QUANT: f32 = 0.00125 / 16.0; // Voltage in 12.5mV
+    const VOLTAGE_QUANT: f32 = 0.00125 / 16.0; // 78.125uV per LSB, in V
     const VOLTAGE_MIN: f32 = 0.0;
     const VOLTAGE_MAX: f32 = VOLTAGE_QUANT * 65535.0;
 
-    const CURRENT_QUANT: f32 = 0.0000015625 / RSENSE;
+    const CURRENT_QUANT: f32 = 0.0000015625 / RSENSE; // 156.25uA per LSB, in A
     const CURRENT_MIN: f32 = CURRENT_QUANT * -32768.0;
     const CURRENT_MAX: f32 = CURRENT_QUANT * 32767.0;
 
@@ -45,7 +47,7 @@ pub fn main() {
         Max17261,
         u8,
         0x36,
-        112,
+        116,
         [
             // 0x00 to 0x10
             (Status, 0x00, 0),
@@ -67,7 +69,6 @@ pub fn main() {
             // 0x10 to 0x1F
             (FullCapRep, 0x10, 16),
             (TimeToEmpty, 0x11, 17),
-            (QRTable00, 0x12, 18),
             (FullSOCThr, 0x13, 19),
             (RCell, 0x14, 20),
             (Reserved0x15, 0x15, 21),
@@ -84,7 +85,6 @@ pub fn main() {
             // 0x20 to 0x2F
             (TimeToFull, 0x20, 32),
             (DevName, 0x21, 33),
-            (QRTable10, 0x22, 34),
             (FullCapNom, 0x23, 35),
             (Reserved0x24, 0x24, 36),
             (Reserved0x25, 0x25, 37),
@@ -101,7 +101,6 @@ pub fn main() {
             // 0x30 to 0x3F
             (Reserved0x30, 0x30, 48),
             (Reserved0x31, 0x31, 49),
-            (QRTable20, 0x32, 50),
             (Reserved0x33, 0x33, 51),
             (DieTemp, 0x34, 52),
             (FullCap, 0x35, 53),
@@ -118,7 +117,6 @@ pub fn main() {
             // 0x40 to 0x4F
             (Reserved0x40, 0x40, 64),
             (Reserved0x41, 0x41, 65),
-            (QRTable30, 0x42, 66),
             (RGain, 0x43, 67),
             (Reserved0x44, 0x44, 68),
             (DQAcc, 0x45, 69),
@@ -166,7 +164,10 @@ pub fn main() {
             (AtTTE, 0xDD, 109),
             (AtAvSOC, 0xDE, 110),
             (AtAvCap, 0xDF, 111)
-        ]
+        ],
+        // Registers are sent LSB first
+        byte_order: LittleEndian,
+        arrays: [(QRTable, 0x12, 0x10, 4, 112)]
     );
 
     register!(Status);
@@ -189,8 +190,8 @@ pub fn main() {
     bitrange_quantized!(VAlrtTh, vmin, 7, 0, u8, 0.02, 0.0, 5.1);
 
     register!(TAlrtTh);
-    bitrange_quantized!(TAlrtTh, tmax, 15, 8, u8, 1.0, -128.0, 128.0);
-    bitrange_quantized!(TAlrtTh, tmin, 7, 0, u8, 1.0, -128.0, 128.0);
+    bitrange_quantized!(TAlrtTh, tmax, 15, 8, i8, 1.0); // -128C to 127C
+    bitrange_quantized!(TAlrtTh, tmin, 7, 0, i8, 1.0); // -128C to 127C
 
     register!(SAlrtTh);
     bitrange_raw!(SAlrtTh, smax, 15, 8, u8);
@@ -366,7 +367,8 @@ pub fn main() {
         TIME_MAX
     );
 
-    register!(QRTable00);
+    // QRTable00, QRTable10, QRTable20 and QRTable30 share one layout
+    register_array!(QRTable);
 
     register!(FullSOCThr); // Full SOC threshold - defaults to 95%
     bitrange_quantized!(
@@ -546,8 +548,6 @@ pub fn main() {
 
     register!(DevName); // Device name, should be 0x4033 for the MAX17261
 
-    register!(QRTable10);
-
     register!(FullCapNom); // Full discharge capacity with compensation under present conditions
     bitrange_quantized!(
         FullCapNom,
@@ -595,8 +595,6 @@ pub fn main() {
     register!(COff); // Current offset (see datasheet
 
     // Registers 0x30 to 0x3F
-    register!(QRTable20);
-
     register!(DieTemp); // Internal die temp
     bitrange_quantized!(
         DieTemp,
@@ -627,27 +625,9 @@ pub fn main() {
 
     register!(VEmpty);
     const VEMPTY_VE_QUANT: f32 = 0.01; // 1mV
-    bitrange_quantized!(
-        VEmpty,
-        ve,
-        15,
-        7,
-        u16,
-        VEMPTY_VE_QUANT,
-        0.0,
-        512.0 * VEMPTY_VE_QUANT // 2^9 * VEMPTY_VR_QUANT
-    );
+    bitrange_quantized!(VEmpty, ve, 15, 7, u16, VEMPTY_VE_QUANT); // 0V to 5.11V
     const VEMPTY_VR_QUANT: f32 = 0.04; // 40mV
-    bitrange_quantized!(
-        VEmpty,
-        vr,
-        6,
-        0,
-        u16,
-        VEMPTY_VR_QUANT,
-        0.0,
-        128.0 * VEMPTY_VR_QUANT // 2^7 * VEMPTY_VR_QUANT
-    );
+    bitrange_quantized!(VEmpty, vr, 6, 0, u16, VEMPTY_VR_QUANT); // 0V to 5.08V
 
     register!(FStat);
     bitfield!(FStat, reldt, 9); // If 1, cell is fully relaxed
@@ -684,8 +664,6 @@ pub fn main() {
         8191.0 * SHDN_CTR_QUANT
     );
 
-    register!(QRTable30);
-
     register!(RGain);
 
     register!(DQAcc); // Charge between relaxation points, debug register
@@ -811,12 +789,35 @@ pub fn main() {
                       // TODO: This is a special register but doesn't have quanitzation values
                       // and has been left as a raw u16 for now
 
-    register!(HibCfg); // See datasheet for mapping bitranges to times
+    register!(HibCfg);
     bitfield!(HibCfg, en_hib, 15);
-    bitrange_raw!(HibCfg, hib_enter_time, 14, 12, u16);
+    // Longest time in seconds below HibThreshold before entering hibernate
+    const HIB_ENTER_TIME: [(u16, f32); 8] = [
+        (0, 5.625),
+        (1, 11.25),
+        (2, 22.5),
+        (3, 45.0),
+        (4, 90.0),
+        (5, 180.0),
+        (6, 360.0),
+        (7, 720.0),
+    ];
+    bitrange_table!(HibCfg, hib_enter_time, 14, 12, u16, HIB_ENTER_TIME);
     bitrange_raw!(HibCfg, hib_threshold, 11, 8, u16);
+    // Time to exit hibernate, (HibExitTime + 1) * 702ms * 2^HibScalar
     bitrange_raw!(HibCfg, hib_exit_time, 4, 3, u16);
-    bitrange_raw!(HibCfg, hib_scalar, 2, 0, u16);
+    // Task period in seconds while in hibernate, 351ms * 2^HibScalar
+    const HIB_SCALAR: [(u16, f32); 8] = [
+        (0, 0.351),
+        (1, 0.702),
+        (2, 1.404),
+        (3, 2.808),
+        (4, 5.616),
+        (5, 11.232),
+        (6, 22.464),
+        (7, 44.928),
+    ];
+    bitrange_table!(HibCfg, hib_scalar, 2, 0, u16, HIB_SCALAR);
 
     register!(Config2);
     bitfield!(Config2, at_rate_en, 13); // When 0, AtRate calculation are disabled
@@ -860,6 +861,13 @@ pub fn main() {
         0.0,
         65535.0 * TIMER_HSB_QUANT
     );
+    // The full 32 bit timer, TimerHSB:TimerLSB in 175.8ms steps
+    bitrange_multi_quantized!(
+        timer,
+        u32,
+        [(TimerHSB, 15, 0), (TimerLSB, 15, 0)],
+        TIMER_LSB_QUANT
+    );
 
     register!(RSense); // Not documented, resister value?
     bitrange_quantized!(
@@ -882,24 +890,13 @@ pub fn main() {
         ocv_low_lim,
         15,
         7,
-        u8,
+        u16,
         OCV_LOW_LIM_QUANT,
         OCV_LOW_LIM_MIN,
         OCV_LOW_LIM_MAX
     );
     const OCV_DELTA_QUANT: f32 = 0.0025; // 2.5mV
-    const OCV_DELTA_MIN: f32 = 0.0; // 0V
-    const OCV_DELTA_MAX: f32 = 128.0 * OCV_DELTA_QUANT; // 2^7 * 2.5mV
-    bitrange_quantized!(
-        ScOcvLim,
-        ocv_delta,
-        6,
-        0,
-        u8,
-        OCV_DELTA_QUANT,
-        OCV_DELTA_MIN,
-        OCV_DELTA_MAX
-    );
+    bitrange_quantized!(ScOcvLim, ocv_delta, 6, 0, u8, OCV_DELTA_QUANT); // 0V to 317.5mV
 
     register!(VGain); // Not documented, voltage gain?
 
@@ -0,0 +1,16 @@
+//! Access to the device behind a peripheral. The peripheral! macro only caches
+//! register values in RAM; an application implements RegisterBus on top of its
+//! I2C or SPI driver so that the generated `bus_read` and `bus_write` methods can
+//! transfer registers, selecting the page of paged registers when needed.
+
+/// Register level reads and writes on a bus. `device` is the bus address of the
+/// peripheral, such as its I2C address. The length of `bytes` is the width of the
+/// register, which can differ between registers of one peripheral, and the bytes are
+/// already in the byte order of the register.
+pub trait RegisterBus {
+    type Error;
+
+    fn read(&mut self, device: u16, address: usize, bytes: &mut [u8]) -> Result<(), Self::Error>;
+
+    fn write(&mut self, device: u16, address: usize, bytes: &[u8]) -> Result<(), Self::Error>;
+}
@@ -0,0 +1,333 @@
+//! # Console
+//!
+//! A small, line oriented command interpreter that works with any peripheral
+//! generated by the peripheral! macro through the Peripheral trait. Output is
+//! written to a `core::fmt::Write`, so the same console can be attached to a
+//! debug UART on a device or to a `String` when testing on a PC.
+//!
+//! Supported commands:
+//! - `list`: list all registers and their addresses
+//! - `list <reg>`: list the fields of a register
+//! - `read <reg>`: print the value of a register
+//! - `write <reg> <val>`: set the value of a register
+//! - `get <reg>.<field>`: print the value of a field
+//! - `set <reg>.<field> <val>`: set the value of a field
+//! - `dump`: print the value of every register
+//!
+//! Registers can be named or given by address, and values can be decimal,
+//! hex (`0x`) or binary (`0b`). An element of a register array is named with its
+//! element, as `QRTable[2]`, and `read QRTable` prints every element of the array.
+use core::fmt;
+use core::fmt::Write;
+
+use crate::{FieldInfo, Peripheral, RegisterInfo};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConsoleError {
+    UnknownCommand,
+    MissingArgument,
+    UnknownRegister,
+    UnknownField,
+    InvalidValue,
+    LineTooLong,
+    Output,
+}
+
+impl fmt::Display for ConsoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ConsoleError::UnknownCommand => "unknown command",
+            ConsoleError::MissingArgument => "missing argument",
+            ConsoleError::UnknownRegister => "unknown register",
+            ConsoleError::UnknownField => "unknown field",
+            ConsoleError::InvalidValue => "invalid value",
+            ConsoleError::LineTooLong => "line too long",
+            ConsoleError::Output => "output error",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl From<fmt::Error> for ConsoleError {
+    fn from(_: fmt::Error) -> Self {
+        ConsoleError::Output
+    }
+}
+
+/// Buffers incoming bytes, for example from a UART, and executes a command
+/// each time a full line has been received.
+pub struct Console<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+    overflow: bool,
+}
+
+impl<const N: usize> Default for Console<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Console<N> {
+    pub const fn new() -> Self {
+        Console {
+            buffer: [0; N],
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// Feeds a single byte into the console. Once a `\n` or `\r` is received the
+    /// buffered line is executed and the result is returned, otherwise None is
+    /// returned. Errors are also reported on `out` as `error: <reason>`.
+    pub fn feed<P: Peripheral, W: Write>(
+        &mut self,
+        byte: u8,
+        peripheral: &mut P,
+        out: &mut W,
+    ) -> Option<Result<(), ConsoleError>> {
+        match byte {
+            b'\n' | b'\r' => {
+                let result = if self.overflow {
+                    Err(ConsoleError::LineTooLong)
+                } else {
+                    match core::str::from_utf8(&self.buffer[..self.len]) {
+                        Ok(line) if line.trim().is_empty() => {
+                            self.len = 0;
+                            return None;
+                        }
+                        Ok(line) => execute(peripheral, line, out),
+                        Err(_) => Err(ConsoleError::UnknownCommand),
+                    }
+                };
+                self.len = 0;
+                self.overflow = false;
+
+                if let Err(e) = result {
+                    let _ = writeln!(out, "error: {}", e);
+                }
+                Some(result)
+            }
+            _ => {
+                if self.len < N {
+                    self.buffer[self.len] = byte;
+                    self.len += 1;
+                } else {
+                    self.overflow = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Executes a single command line against a peripheral, writing the result to `out`.
+pub fn execute<P: Peripheral, W: Write>(
+    peripheral: &mut P,
+    line: &str,
+    out: &mut W,
+) -> Result<(), ConsoleError> {
+    let mut args = line.split_whitespace();
+    let command = args.next().ok_or(ConsoleError::UnknownCommand)?;
+
+    match command {
+        "list" => match args.next() {
+            Some(name) => {
+                let register = find_register(peripheral, name)?;
+                for field in peripheral.fields(register.index) {
+                    write!(out, "{}.{} [", register, field.name)?;
+                    if field.slices.is_empty() {
+                        write!(out, "{}:{}", field.msb, field.lsb)?;
+                    }
+                    for (i, (msb, lsb)) in field.slices.iter().enumerate() {
+                        if i > 0 {
+                            write!(out, ",")?;
+                        }
+                        write!(out, "{}:{}", msb, lsb)?;
+                    }
+                    writeln!(out, "]")?;
+                }
+                Ok(())
+            }
+            None => {
+                for register in peripheral.registers() {
+                    write!(out, "{} @ 0x{:02X}", register, register.address)?;
+                    if let Some(page) = register.page {
+                        write!(out, " page {}", page)?;
+                    }
+                    writeln!(out)?;
+                }
+                Ok(())
+            }
+        },
+        "read" => {
+            for register in find_registers(peripheral, next(&mut args)?)? {
+                print_register(peripheral, register, out)?;
+            }
+            Ok(())
+        }
+        "write" => {
+            let register = find_register(peripheral, next(&mut args)?)?;
+            let value = parse_unsigned(next(&mut args)?, register.width)?;
+            peripheral.write_register(register.index, value);
+            print_register(peripheral, &register, out)
+        }
+        "get" => {
+            let (register, field) = find_field(peripheral, next(&mut args)?)?;
+            print_field(peripheral, &register, &field, out)
+        }
+        "set" => {
+            let (register, field) = find_field(peripheral, next(&mut args)?)?;
+            let arg = next(&mut args)?;
+            let bits = if field.signed {
+                parse_signed(arg, field.width())?
+            } else {
+                parse_unsigned(arg, field.width())?
+            };
+
+            let contents = peripheral.read_register(register.index);
+            peripheral.write_register(register.index, field.insert(contents, bits));
+            print_field(peripheral, &register, &field, out)
+        }
+        "dump" => {
+            for register in peripheral.registers() {
+                print_register(peripheral, register, out)?;
+            }
+            Ok(())
+        }
+        _ => Err(ConsoleError::UnknownCommand),
+    }
+}
+
+fn next<'a>(args: &mut impl Iterator<Item = &'a str>) -> Result<&'a str, ConsoleError> {
+    args.next().ok_or(ConsoleError::MissingArgument)
+}
+
+/// The registers `name` refers to: a register by name or address, an element of an
+/// array as `QRTable[2]`, or every element of an array by the name of the array
+fn find_registers<P: Peripheral>(
+    peripheral: &P,
+    name: &str,
+) -> Result<&'static [RegisterInfo], ConsoleError> {
+    let registers = peripheral.registers();
+    let (name, element) = match name.strip_suffix(']').and_then(|n| n.split_once('[')) {
+        Some((name, element)) => {
+            let element = usize::try_from(parse_number(element)?);
+            (name, Some(element.map_err(|_| ConsoleError::InvalidValue)?))
+        }
+        None => (name, None),
+    };
+    let address = parse_number(name).ok();
+    let matches = |r: &RegisterInfo| {
+        (r.name.eq_ignore_ascii_case(name) || Some(r.address as u128) == address)
+            && (element.is_none() || r.element == element)
+    };
+
+    let start = registers
+        .iter()
+        .position(matches)
+        .ok_or(ConsoleError::UnknownRegister)?;
+    // The elements of an array are listed together
+    let len = match (address, element) {
+        (None, None) => registers[start..].iter().take_while(|r| matches(r)).count(),
+        _ => 1,
+    };
+    Ok(&registers[start..start + len])
+}
+
+/// A single register, so an array must be given with its element
+fn find_register<P: Peripheral>(peripheral: &P, name: &str) -> Result<RegisterInfo, ConsoleError> {
+    match find_registers(peripheral, name)? {
+        [register] => Ok(*register),
+        _ => Err(ConsoleError::MissingArgument),
+    }
+}
+
+fn find_field<P: Peripheral>(
+    peripheral: &P,
+    path: &str,
+) -> Result<(RegisterInfo, FieldInfo), ConsoleError> {
+    let (register, field) = path.split_once('.').ok_or(ConsoleError::MissingArgument)?;
+    let register = find_register(peripheral, register)?;
+    let field = peripheral
+        .fields(register.index)
+        .iter()
+        .find(|f| f.name.eq_ignore_ascii_case(field))
+        .copied()
+        .ok_or(ConsoleError::UnknownField)?;
+    Ok((register, field))
+}
+
+fn print_register<P: Peripheral, W: Write>(
+    peripheral: &P,
+    register: &RegisterInfo,
+    out: &mut W,
+) -> Result<(), ConsoleError> {
+    let value = peripheral.read_register(register.index);
+    let digits = register.width.div_ceil(4) as usize;
+    writeln!(
+        out,
+        "{} @ 0x{:02X} = 0x{:0digits$X}",
+        register, register.address, value
+    )?;
+    Ok(())
+}
+
+fn print_field<P: Peripheral, W: Write>(
+    peripheral: &P,
+    register: &RegisterInfo,
+    field: &FieldInfo,
+    out: &mut W,
+) -> Result<(), ConsoleError> {
+    let raw = field.extract(peripheral.read_register(register.index));
+    if field.signed {
+        let shift = 128 - field.width();
+        let value = ((raw << shift) as i128) >> shift;
+        writeln!(out, "{}.{} = {}", register, field.name, value)?;
+    } else {
+        writeln!(out, "{}.{} = {}", register, field.name, raw)?;
+    }
+    Ok(())
+}
+
+/// Digits and radix of a number with an optional `0x` / `0b` prefix, in either case
+fn split_radix(s: &str) -> (&str, u32) {
+    if let Some(hex) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b").or(s.strip_prefix("0B")) {
+        (bin, 2)
+    } else {
+        (s, 10)
+    }
+}
+
+fn parse_number(s: &str) -> Result<u128, ConsoleError> {
+    let (digits, radix) = split_radix(s);
+    u128::from_str_radix(digits, radix).map_err(|_| ConsoleError::InvalidValue)
+}
+
+fn parse_unsigned(s: &str, width: u32) -> Result<u128, ConsoleError> {
+    let value = parse_number(s)?;
+    if width < 128 && value >> width != 0 {
+        return Err(ConsoleError::InvalidValue);
+    }
+    Ok(value)
+}
+
+/// Parses a signed value and returns its two's complement encoding for `width` bits
+fn parse_signed(s: &str, width: u32) -> Result<u128, ConsoleError> {
+    let value = match s.strip_prefix('-') {
+        Some(magnitude) => 0i128
+            .checked_sub_unsigned(parse_number(magnitude)?)
+            .ok_or(ConsoleError::InvalidValue)?,
+        // Hex and binary values are the raw encoding of the field
+        None if split_radix(s).1 != 10 => return parse_unsigned(s, width),
+        None => i128::try_from(parse_number(s)?).map_err(|_| ConsoleError::InvalidValue)?,
+    };
+
+    let min = i128::MIN >> (128 - width);
+    if value < min || value > !min {
+        return Err(ConsoleError::InvalidValue);
+    }
+    Ok(value as u128 & (u128::MAX >> (128 - width)))
+}
@@ -0,0 +1,27 @@
+//! Integer helpers used by the bitrange_fixed! macro. A fixed point field has an
+//! LSB weight of `numerator / denominator` output units, for example 625 / 8 µV,
+//! and all conversions are done with i64 math so that no floating point code is
+//! needed on MCUs without an FPU.
+
+/// Divides `n` by `d`, rounding to the nearest integer with ties away from zero.
+pub const fn div_round(n: i64, d: i64) -> i64 {
+    let quotient = n / d;
+    let remainder = n % d;
+    if 2 * remainder.abs() < d.abs() {
+        quotient
+    } else if (n < 0) == (d < 0) {
+        quotient + 1
+    } else {
+        quotient - 1
+    }
+}
+
+/// Converts a raw field value to a scaled integer, `raw * numerator / denominator`
+pub const fn raw_to_scaled(raw: i64, numerator: i64, denominator: i64) -> i64 {
+    div_round(raw * numerator, denominator)
+}
+
+/// Converts a scaled integer to the nearest raw field value, `value * denominator / numerator`
+pub const fn scaled_to_raw(value: i64, numerator: i64, denominator: i64) -> i64 {
+    div_round(value * denominator, numerator)
+}
@@ -13,119 +13,501 @@
 ///
 ///
 ///
-
+pub mod bus;
+pub mod console;
+pub mod fixed;
+pub mod lookup;
+pub mod mmio;
+pub mod pmbus;
+pub mod quantize;
+pub mod scattered;
+
+pub use quantize::Rounding;
+
+#[cfg(feature = "uom")]
+pub use uom;
+
+/// Errors returned by field setters. `T` is the type taken by the setter, `f32`
+/// for quantized fields and the raw or output type for raw and fixed-point fields.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum Errors {
+pub enum Errors<T = f32> {
+    /// The value doesn't fall on a quantization step of an exact-only field
     QuantizationError,
+    /// The value is below the smallest value the field accepts
+    TooLow { requested: T, min: T, max: T },
+    /// The value is above the largest value the field accepts
+    TooHigh { requested: T, min: T, max: T },
+}
+
+impl<T> Errors<T> {
+    /// Converts the values carried by a range error, for example from f32 to a
+    /// typed quantity
+    pub fn map<U>(self, f: impl Fn(T) -> U) -> Errors<U> {
+        match self {
+            Errors::QuantizationError => Errors::QuantizationError,
+            Errors::TooLow {
+                requested,
+                min,
+                max,
+            } => Errors::TooLow {
+                requested: f(requested),
+                min: f(min),
+                max: f(max),
+            },
+            Errors::TooHigh {
+                requested,
+                min,
+                max,
+            } => Errors::TooHigh {
+                requested: f(requested),
+                min: f(min),
+                max: f(max),
+            },
+        }
+    }
+}
+
+/// Returned by the `_saturating` setters: the value that was stored, and whether
+/// the requested value was outside of the field and clamped to its min or max.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Saturated<T> {
+    pub value: T,
+    pub clamped: bool,
+}
+
+/// Order of the bytes of a register on the bus and in `to_bytes` / `from_bytes`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    /// Most significant byte first
+    #[default]
+    BigEndian,
+    /// Least significant byte first, such as the Max17261
+    LittleEndian,
+}
+
+impl ByteOrder {
+    /// Writes the low `bytes.len()` bytes of `value` into `bytes`
+    pub fn encode(self, value: u128, bytes: &mut [u8]) {
+        let len = bytes.len();
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let shift = match self {
+                ByteOrder::BigEndian => len - 1 - i,
+                ByteOrder::LittleEndian => i,
+            };
+            *byte = (value >> (shift * 8)) as u8;
+        }
+    }
+
+    /// Reads a value of `bytes.len()` bytes
+    pub fn decode(self, bytes: &[u8]) -> u128 {
+        let len = bytes.len();
+        bytes.iter().enumerate().fold(0, |value, (i, &byte)| {
+            let shift = match self {
+                ByteOrder::BigEndian => len - 1 - i,
+                ByteOrder::LittleEndian => i,
+            };
+            value | (byte as u128) << (shift * 8)
+        })
+    }
+}
+
+/// What one step of a register address covers, used by `load_bytes` and
+/// `store_bytes` of a peripheral to find the address of the next register.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Addressing {
+    /// Each register has its own address whatever its width, such as the Max17261
+    #[default]
+    Register,
+    /// Each byte has its own address, so a 16 bit register takes two addresses
+    Byte,
+}
+
+/// Errors returned by the `load_bytes` and `store_bytes` methods of a peripheral.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ByteError {
+    /// There is no register at this address
+    UnknownAddress(usize),
+    /// The bytes end part way through a register
+    Length,
+}
+
+/// Describes a single register of a peripheral. A table of these is generated
+/// by the peripheral! macro and is used by tooling such as the console.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegisterInfo {
+    pub name: &'static str,
+    pub address: usize,
+    pub index: usize,
+    pub width: u32,
+    /// Page of a register in a paged map, None if it is accessible from every page
+    pub page: Option<u32>,
+    /// Element of a register array, None for a single register
+    pub element: Option<usize>,
+}
+
+impl core::fmt::Display for RegisterInfo {
+    /// The name of the register, with the element of an array as in `QRTable[2]`
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.element {
+            Some(element) => write!(f, "{}[{}]", self.name, element),
+            None => f.write_str(self.name),
+        }
+    }
+}
+
+/// Describes an array of repeated registers declared in the `arrays:` section of
+/// the peripheral! macro. Element `i` is at `address + i * stride` and is stored at
+/// `index + i`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegisterArray {
+    pub name: &'static str,
+    pub address: usize,
+    pub stride: usize,
+    pub count: usize,
+    pub index: usize,
+    /// Width of each element in bits
+    pub width: u32,
+    pub byte_order: ByteOrder,
+}
+
+/// What the device does with a field when its register is read or written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Access {
+    /// Plain read / write
+    #[default]
+    ReadWrite,
+    /// Writing 1 clears the field, writing 0 has no effect
+    W1C,
+    /// Writing 0 clears the field, writing 1 has no effect
+    W0C,
+    /// The device clears the field when the register is read
+    RC,
+    /// The device sets the field when the register is read
+    RS,
+}
+
+/// Describes a named field within a register. Each field macro generates one
+/// of these as an associated constant on the register, named after the field.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// Highest and lowest bit of the field
+    pub msb: u32,
+    pub lsb: u32,
+    pub signed: bool,
+    pub access: Access,
+    /// The `(msb, lsb)` slices of a bitrange_scattered! field, most significant part
+    /// of the value first. Empty for a field that occupies every bit from msb to lsb.
+    pub slices: &'static [(u32, u32)],
+}
+
+impl FieldInfo {
+    /// Number of bits of the value of the field
+    pub const fn width(&self) -> u32 {
+        if self.slices.is_empty() {
+            self.msb - self.lsb + 1
+        } else {
+            scattered::width(self.slices)
+        }
+    }
+
+    /// Bits of the register occupied by the field
+    pub const fn mask(&self) -> u128 {
+        if self.slices.is_empty() {
+            (u128::MAX >> (u128::BITS - self.width())) << self.lsb
+        } else {
+            scattered::mask(self.slices)
+        }
+    }
+
+    /// Bits of the fields of `fields` with the access `access`, used to collect the
+    /// W1C and W0C masks of a register
+    #[doc(hidden)]
+    pub const fn access_mask(fields: &[FieldInfo], access: Access) -> u128 {
+        let mut mask = 0;
+        let mut i = 0;
+        while i < fields.len() {
+            if fields[i].access as u8 == access as u8 {
+                mask |= fields[i].mask();
+            }
+            i += 1;
+        }
+        mask
+    }
+
+    /// The raw value of the field in `contents`
+    pub fn extract(&self, contents: u128) -> u128 {
+        if self.slices.is_empty() {
+            (contents & self.mask()) >> self.lsb
+        } else {
+            scattered::gather(contents, self.slices)
+        }
+    }
+
+    /// `contents` with the field replaced by the raw `value`
+    pub fn insert(&self, contents: u128, value: u128) -> u128 {
+        if self.slices.is_empty() {
+            (contents & !self.mask()) | ((value << self.lsb) & self.mask())
+        } else {
+            scattered::scatter(contents, self.slices, value)
+        }
+    }
+}
+
+/// Implemented by the register structs of register!, register_array! and
+/// register_overlay!, so that a field can be checked to belong to the register.
+pub trait RegisterFields {
+    /// Index of the backing register
+    fn index(&self) -> usize;
+
+    /// True if `field` is in the access list of this register, such as `Fault` of
+    /// `register!(Status, access: [Fault])`
+    fn has_field(field: &FieldInfo) -> bool;
+}
+
+/// Type erased access to a peripheral generated by peripheral!. Register
+/// values are widened to u128 so that the same code, for example the console,
+/// can operate on any register size.
+pub trait Peripheral {
+    /// The register_backer! struct holding each register
+    type Backer: Backer;
+
+    /// All registers listed in the peripheral! register map
+    fn registers(&self) -> &'static [RegisterInfo];
+
+    /// Fields of the register at `index`, if they were listed in the `fields`
+    /// section of the peripheral! macro.
+    fn fields(&self, _index: usize) -> &'static [FieldInfo] {
+        &[]
+    }
+
+    fn read_register(&self, index: usize) -> u128;
+
+    fn write_register(&mut self, index: usize, value: u128);
+}
+
+/// Implemented by the struct generated by register_backer!, so that a peripheral
+/// can find the type and width of its registers.
+pub trait Backer: Copy {
+    /// The unsigned primitive holding a register
+    type Value: Copy;
+
+    /// Number of bits used of each Value
+    const WIDTH: u32;
+
+    /// The register holding `bits`, masked to the width
+    #[doc(hidden)]
+    fn from_bits(bits: u128) -> Self;
+
+    /// Contents of the register widened to u128
+    #[doc(hidden)]
+    fn bits(&self) -> u128;
+}
+
+/// Implemented by a peripheral! and by each of its `variants`, so that the register
+/// accessors, fields and generic code written for the peripheral work across the
+/// device family. `Base` is the peripheral the variants were declared with.
+pub trait Family: Peripheral {
+    type Base: Peripheral;
+
+    /// Whether the register at each index exists on this member of the family
+    const PRESENT: &'static [bool];
+
+    /// Pointer to the backing register at `index`
+    fn backer(&self, index: usize) -> mmio::BackerPtr<<Self::Base as Peripheral>::Backer>;
+}
+
+/// True if `name` is one of `members`, used by the `only = [...]` option of peripheral!
+#[doc(hidden)]
+pub const fn is_member(members: &[&str], name: &str) -> bool {
+    let mut i = 0;
+    while i < members.len() {
+        let member = members[i].as_bytes();
+        let name = name.as_bytes();
+        if member.len() == name.len() {
+            let mut j = 0;
+            while j < name.len() && member[j] == name[j] {
+                j += 1;
+            }
+            if j == name.len() {
+                return true;
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+/// A range of bits of a register, from `start_bit` to `stop_bit` inclusive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BitRange {
+    pub start_bit: u32,
+    pub stop_bit: u32,
+}
+
+impl BitRange {
+    pub fn new(start_bit: u32, stop_bit: u32) -> Self {
+        assert!(
+            start_bit <= stop_bit,
+            "Start bit must be less than or equal to stop bit"
+        );
+        BitRange {
+            start_bit,
+            stop_bit,
+        }
+    }
 }
 
+#[doc(hidden)]
+pub use paste;
+
 /// The register_backer! macro is used to generate a RegisterBacker struct that is used
 /// by subsequent macros, such as peripheral!. The generated struct has accessors
 /// to get / set / toggle / clear bits, as well as get a range of bits.
+///
+/// Any unsigned primitive from u8 to u128 can be used. An optional width in bits stores
+/// narrower registers in a wider type, such as `register_backer!(Register, u32, 24)` for
+/// 24 bit registers; the bits above the width are kept clear and bit indexes at or
+/// above the width panic.
+///
+/// `register_backer!(Register, u16)` also defines `RegisterType`, `RegisterBacker` and
+/// `REGISTER_WIDTH` for a single peripheral! in scope. Written as
+/// `register_backer!(Reg16: u16)` only the struct is generated, which is then given to
+/// each peripheral, as `peripheral!(Max17261: Reg16, ...)`, so that several backers and
+/// peripherals can share one module.
 #[macro_export]
 macro_rules! register_backer {
-    ($reg_name:ident, $reg_type:ty) => {
-        pub type RegisterType = $reg_type;
-
-        pub type RegisterBacker = $reg_name;
+    ($reg_name:ident: $reg_type:ty) => {
+        $crate::register_backer!($reg_name: $reg_type, <$reg_type>::BITS);
+    };
+    ($reg_name:ident: $reg_type:ty, $width:expr) => {
+        const _: () = assert!(
+            $reg_name::WIDTH > 0 && $reg_name::WIDTH <= <$reg_type>::BITS,
+            concat!(
+                "The width of ",
+                stringify!($reg_name),
+                " must be between 1 and the bits of ",
+                stringify!($reg_type)
+            )
+        );
 
         #[derive(Copy, Clone)]
         pub struct $reg_name {
             contents: $reg_type,
         }
 
-        #[derive(Copy, Clone)]
-        pub struct BitRange {
-            start_bit: $reg_type,
-            stop_bit: $reg_type,
-        }
+        impl $crate::Backer for $reg_name {
+            type Value = $reg_type;
+            const WIDTH: u32 = $reg_name::WIDTH;
 
-        impl BitRange {
-            pub fn new(start_bit: $reg_type, stop_bit: $reg_type) -> Self {
-                assert!(
-                    start_bit <= stop_bit,
-                    "Start bit must be less than or equal to stop bit"
-                );
-                BitRange {
-                    start_bit,
-                    stop_bit,
-                }
+            fn from_bits(bits: u128) -> Self {
+                Self::new(bits as $reg_type)
+            }
+
+            fn bits(&self) -> u128 {
+                self.contents as u128
             }
         }
 
         impl $reg_name {
+            /// Number of bits used of each register
+            pub const WIDTH: u32 = $width;
+
+            /// Mask of the bits within the register width
+            const WIDTH_MASK: $reg_type = <$reg_type>::MAX >> (<$reg_type>::BITS - Self::WIDTH);
+
             pub fn new(contents: $reg_type) -> Self {
-                Self { contents: contents }
+                Self {
+                    contents: contents & Self::WIDTH_MASK,
+                }
             }
 
             pub fn contents(&self) -> $reg_type {
                 self.contents
             }
 
-            pub fn set_bit(&mut self, bit: $reg_type) -> &mut Self {
-                self.contents |= 1 << (bit as $reg_type);
+            fn store(&mut self, contents: $reg_type) {
+                self.contents = contents;
+            }
+
+            fn bit(bit: u32) -> $reg_type {
+                assert!(bit < Self::WIDTH, "Bit must be within the register");
+                (1 as $reg_type) << bit
+            }
+
+            pub fn set_bit(&mut self, bit: u32) -> &mut Self {
+                self.store(self.contents() | Self::bit(bit));
                 self
             }
 
             pub fn set_all(&mut self) -> &mut Self {
-                self.contents = <$reg_type>::MAX;
+                self.store(Self::WIDTH_MASK);
                 self
             }
 
-            pub fn clear_bit(&mut self, bit: $reg_type) -> &mut Self {
-                self.contents &= !(1 << (bit as $reg_type));
+            pub fn clear_bit(&mut self, bit: u32) -> &mut Self {
+                self.store(self.contents() & !Self::bit(bit));
                 self
             }
 
             pub fn clear_all(&mut self) -> &mut Self {
-                self.contents = 0;
+                self.store(0);
                 self
             }
 
-            pub fn toggle_bit(&mut self, bit: $reg_type) -> &mut Self {
-                self.contents ^= 1 << (bit as $reg_type);
+            pub fn toggle_bit(&mut self, bit: u32) -> &mut Self {
+                self.store(self.contents() ^ Self::bit(bit));
                 self
             }
 
-            pub fn is_set(&self, bit: $reg_type) -> bool {
-                self.contents & (1 << (bit as $reg_type)) != 0
+            pub fn is_set(&self, bit: u32) -> bool {
+                self.contents() & Self::bit(bit) != 0
             }
 
-            pub fn is_clear(&self, bit: $reg_type) -> bool {
-                self.contents & (1 << (bit as $reg_type)) == 0
+            pub fn is_clear(&self, bit: u32) -> bool {
+                self.contents() & Self::bit(bit) == 0
             }
 
             pub fn update(&mut self, new_val: $reg_type) -> &mut Self {
-                self.contents = new_val;
+                self.store(new_val & Self::WIDTH_MASK);
                 self
             }
 
-            pub fn get_range(&self, range: BitRange) -> $reg_type {
-                ((self.contents & self.mask(range)) >> range.start_bit) as $reg_type
+            pub fn get_range(&self, range: $crate::BitRange) -> $reg_type {
+                (self.contents() & self.mask(range)) >> range.start_bit
             }
 
-            pub fn mask(&self, br: BitRange) -> $reg_type {
-                let exp = (2 as $reg_type).overflowing_pow((br.stop_bit + 1 - br.start_bit) as u32);
-                if exp.1 == true {
-                    <$reg_type>::MAX << br.start_bit
-                } else {
-                    (exp.0 - 1) << br.start_bit
-                }
+            pub fn mask(&self, br: $crate::BitRange) -> $reg_type {
+                assert!(br.stop_bit < Self::WIDTH, "Stop bit must be within the register");
+                let width = br.stop_bit + 1 - br.start_bit;
+                (<$reg_type>::MAX >> (<$reg_type>::BITS - width)) << br.start_bit & Self::WIDTH_MASK
             }
 
-            pub fn clear_range(&mut self, range: BitRange) -> &mut Self {
-                self.contents = self.contents & !self.mask(range);
+            pub fn clear_range(&mut self, range: $crate::BitRange) -> &mut Self {
+                self.store(self.contents() & !self.mask(range));
                 self
             }
 
-            pub fn set_range(&mut self, range: BitRange, val: $reg_type) -> &mut Self {
-                self.clear_range(range); // Clear bits
+            pub fn set_range(&mut self, range: $crate::BitRange, val: $reg_type) -> &mut Self {
+                let cleared = self.contents() & !self.mask(range); // Clear bits
                 let masked_val = self.mask(range) & (val << range.start_bit); // Mask input
-                self.contents = self.contents | masked_val;
+                self.store(cleared | masked_val);
                 self
             }
         }
     };
+    ($reg_name:ident, $reg_type:ty $(, $width:expr)?) => {
+        $crate::register_backer!($reg_name: $reg_type $(, $width)?);
+
+        pub type RegisterType = $reg_type;
+
+        pub type RegisterBacker = $reg_name;
+
+        /// Number of bits used of each RegisterType
+        pub const REGISTER_WIDTH: u32 = $reg_name::WIDTH;
+
+        pub use $crate::BitRange;
+    };
 }
 
 /// This macro is used to generate a struct called a peripheral that allocates
@@ -138,49 +520,309 @@ macro_rules! register_backer {
 /// Note, the number of registers should be >= the number of registers in the list of tuples.
 /// or there will be a panic at runtime if you try to access a register that is outside of
 /// the range of the number of registers.
+///
+/// Registers default to the width of the RegisterType of register_backer!. A narrower
+/// register, such as an 8 bit config register next to 24 bit data registers, is given
+/// its width in bits with `(Name, address, index, width = 8)`. RegisterType must be
+/// wide enough for the widest register, values are masked to the register width, and
+/// bus transfers use the number of bytes of the register. Each register is still stored
+/// as a RegisterType, the width doesn't reduce the memory used.
+///
+/// Multi-byte registers are big endian unless `byte_order: LittleEndian` is given after
+/// the register map, and a single register can override this with
+/// `(Name, address, index, byte_order = BigEndian)`. The byte order is used by
+/// `to_bytes` / `from_bytes` of the registers, by `load_bytes` / `store_bytes`, which
+/// transfer registers at consecutive addresses, and by bus transfers. Consecutive
+/// registers are one address apart unless `addressing: Byte` follows the byte order, for
+/// maps where each byte has its own address and a 16 bit register takes two.
+///
+/// Registers of a paged (banked) map reuse addresses on different pages. A register is
+/// given a page with `(Name, address, index, page = n)`, and the register that selects
+/// the page is named with `page_select: Register` after the register map. The
+/// `bus_read` and `bus_write` methods then write the page select register before
+/// accessing a paged register, but only if the device is on a different page. Registers
+/// without a page, including the page select register, are accessible from every page.
+///
+/// Registers renamed between datasheet revisions, or documented under two names, are
+/// declared with `aliases: [(Alias, Canonical), ...]` after the variants. An alias shares
+/// the address, index and storage of its canonical register, and register! on the alias
+/// gives a second accessor with its own fields. Likewise each register_overlay! is
+/// declared with its register in `overlays: [(Overlay, Register), ...]` after the
+/// aliases, so that the W1C and W0C flags of the overlay are in WRITE_MASKS.
+///
+/// Registers are cached in RAM unless `storage: MemoryMapped` is given after the byte
+/// order, for on-chip peripherals. `new` then takes the base address of the peripheral and
+/// every register is accessed in place, at the base address plus its address in bytes,
+/// see the mmio module.
+///
+/// An optional `arrays: [(Name, base address, stride, count, base index), ...]` section
+/// declares repeated registers that share one field layout, such as the QRTable
+/// registers of the Max17261. Element `i` is at `base address + i * stride` and is
+/// stored at `base index + i`, so `count` consecutive indexes must be free. Use
+/// register_array! to generate the indexed accessor. A `width = n` and `byte_order = ...`
+/// can be added to the tuple as for other registers.
+///
+/// An optional `fields: [(Register, [Field, ...]), ...]` section can follow the register
+/// map to expose the named fields of each register through the Peripheral trait.
+///
+/// The address, index, width, byte order and page of each register are associated
+/// constants of the peripheral, such as `Max17261::Status_INDEX`. Named with its backer,
+/// as `peripheral!(Max17261: Reg16, u8, 0x36, ...)`, nothing else is generated, so any
+/// number of peripherals can share a module. Without a backer the peripheral uses
+/// RegisterBacker, and `RegisterAddress`, `RegisterIndex` and `PeripheralType` are also
+/// generated for the macros that don't name a peripheral.
+///
+/// Members of a device family are declared together with `variants: [Max17260, Max17263]`
+/// after the register map. Each variant is a peripheral with the same register indexes,
+/// and a register that only some members have is given `only = [Max17260, ...]`, which
+/// may list the peripheral itself. A register that differs on a variant is listed twice,
+/// under two names and indexes, with `only`. The peripheral and its variants implement
+/// Family, and the register accessors and multi register fields are implemented for
+/// every member, so generic code can use `P: Family<Base = Max17261>`. Accessing a
+/// register that a member doesn't have is a compile error, as is a name in `only` that
+/// isn't the peripheral or one of its variants.
 #[macro_export]
 macro_rules! peripheral {
     //($enum_name:ident, $enum_type:ty, [$(($name:ident, $value:literal)),+]) => {
 
-    ($peripheral_name:ident, $address_type:ty, $i2c_addr:literal, $count:literal, [$(($register:ident, $addr:literal, $index:literal)),+]) => {
-        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-        pub enum RegisterAddress {
+    ($peripheral_name:ident: $backer:ident, $address_type:ty, $i2c_addr:literal, $count:literal, [$(($register:ident, $addr:literal, $index:literal $(, width = $width:literal)? $(, byte_order = $order:ident)? $(, page = $page:literal)? $(, only = [$($only:ident),+])?)),+]
+        $(, page_select: $page_register:ident)?
+        $(, byte_order: $byte_order:ident)?
+        $(, addressing: $addressing:ident)?
+        $(, storage: $storage:ident)?
+        $(, variants: [$($variant:ident),+])?
+        $(, aliases: [$(($alias:ident, $canonical:ident)),+])?
+        $(, overlays: [$(($overlay:ident, $overlay_register:ident)),+])?
+        $(, arrays: [$(($array:ident, $array_addr:literal, $array_stride:literal, $array_count:literal, $array_index:literal $(, width = $array_width:literal)? $(, byte_order = $array_order:ident)?)),+])?
+        $(, fields: [$(($field_register:ident, [$($field:ident),+])),+])?) => {
+        $crate::peripheral!(@members $peripheral_name, [$peripheral_name $($(, $variant)+)?],
+            $backer, $address_type, $i2c_addr, $count, [$(($register, $addr, $index $(, width = $width)? $(, byte_order = $order)? $(, page = $page)? $(, only = [$($only),+])?)),+]
+            $(, page_select: $page_register)?
+            $(, byte_order: $byte_order)?
+            $(, addressing: $addressing)?
+            $(, storage: $storage)?
+            $(, aliases: [$(($alias, $canonical)),+])?
+            $(, overlays: [$(($overlay, $overlay_register)),+])?
+            $(, arrays: [$(($array, $array_addr, $array_stride, $array_count, $array_index $(, width = $array_width)? $(, byte_order = $array_order)?)),+])?
+            $(, fields: [$(($field_register, [$($field),+])),+])?);
+
+        $crate::paste::paste! {
+            // register!, register_array! and register_overlay! define the W1C and W0C masks
+            // of their register on the peripheral, which take precedence over these defaults
+            #[allow(dead_code)]
+            trait [<$peripheral_name WriteMasks>] {
+                $(const [<$register _WRITE_MASKS>]: (u128, u128) = (0, 0);)+
+                $($(const [<$alias _WRITE_MASKS>]: (u128, u128) = (0, 0);)+)?
+                $($(const [<$overlay _WRITE_MASKS>]: (u128, u128) = (0, 0);)+)?
+                $($(const [<$array _WRITE_MASKS>]: (u128, u128) = (0, 0);)+)?
+            }
+
+            impl [<$peripheral_name WriteMasks>] for $peripheral_name {}
+        }
+
+        // A misspelt member in `only` would silently leave the register out
+        const _: () = {
+            let members: &[&str] = &[stringify!($peripheral_name) $($(, stringify!($variant))+)?];
+            $($($(
+                assert!(
+                    $crate::is_member(members, stringify!($only)),
+                    concat!(stringify!($only), " in the only list of ", stringify!($register), " isn't ", stringify!($peripheral_name), " or one of its variants")
+                );
+            )+)?)+
+        };
+    };
+    (@members $family:ident, [$member:ident $(, $rest:ident)*], $($definition:tt)*) => {
+        $crate::peripheral!(@member $family, $member: $($definition)*);
+        $crate::peripheral!(@members $family, [$($rest),*], $($definition)*);
+    };
+    (@members $family:ident, [], $($definition:tt)*) => {};
+    (@legacy aliases: [$(($alias:ident, $canonical:ident)),+]) => {
+        #[allow(non_upper_case_globals)]
+        impl RegisterAddress {
             $(
-                $register = $addr,
+                pub const $alias: usize = RegisterAddress::$canonical;
             )+
         }
 
-        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-        pub enum RegisterIndex {
+        #[allow(non_upper_case_globals)]
+        impl RegisterIndex {
             $(
-                $register = $index,
+                pub const $alias: RegisterIndex = RegisterIndex::$canonical;
             )+
         }
-
+    };
+    (@legacy $section:ident: $value:tt) => {};
+    (@memory_mapped) => { false };
+    (@memory_mapped MemoryMapped) => { true };
+    (@storage $backer:ident, $count:literal) => { $crate::mmio::Cached<$backer, $count> };
+    (@storage $backer:ident, $count:literal, MemoryMapped) => { $crate::mmio::MemoryMapped };
+    (@new $backer:ident, $count:literal) => {
+        pub fn new() -> Self {
+            Self::with_registers($crate::mmio::Cached::new([$backer::new(0); $count]))
+        }
+    };
+    (@new $backer:ident, $count:literal, MemoryMapped) => {
+        /// Peripheral with its registers at `base`, see MemoryMapped::new
+        ///
+        /// # Safety
+        ///
+        /// `base` must be valid for every register of the peripheral at its address,
+        /// and aligned so that every register is aligned to its width.
+        pub unsafe fn new(base: *mut u8) -> Self {
+            Self::with_registers($crate::mmio::MemoryMapped::new(base))
+        }
+    };
+    (@member $family:ident, $peripheral_name:ident: $backer:ident, $address_type:ty, $i2c_addr:literal, $count:literal, [$(($register:ident, $addr:literal, $index:literal $(, width = $width:literal)? $(, byte_order = $order:ident)? $(, page = $page:literal)? $(, only = [$($only:ident),+])?)),+]
+        $(, page_select: $page_register:ident)?
+        $(, byte_order: $byte_order:ident)?
+        $(, addressing: $addressing:ident)?
+        $(, storage: $storage:ident)?
+        $(, aliases: [$(($alias:ident, $canonical:ident)),+])?
+        $(, overlays: [$(($overlay:ident, $overlay_register:ident)),+])?
+        $(, arrays: [$(($array:ident, $array_addr:literal, $array_stride:literal, $array_count:literal, $array_index:literal $(, width = $array_width:literal)? $(, byte_order = $array_order:ident)?)),+])?
+        $(, fields: [$(($field_register:ident, [$($field:ident),+])),+])?) => {
+        $crate::paste::paste! {
         pub struct $peripheral_name {
-            registers: [RegisterBacker; $count],
+            registers: $crate::peripheral!(@storage $backer, $count $(, $storage)?),
             address_index: [$address_type; $count],
             i2c_addr: u16,
+            // Page last selected on the device, None until known
+            device_page: Option<u32>,
+        }
+
+        #[allow(non_upper_case_globals)]
+        impl $peripheral_name {
+            $(
+                pub const [<$register _ADDRESS>]: usize = $addr;
+
+                pub const [<$register _INDEX>]: usize = $index;
+
+                pub const [<$register _WIDTH>]: u32 = {
+                    let width = <$backer as $crate::Backer>::WIDTH;
+                    $(let width = $width;)?
+                    assert!(
+                        width <= <$backer as $crate::Backer>::WIDTH && width % 8 == 0,
+                        concat!(stringify!($register), " must be a whole number of bytes no wider than ", stringify!($backer))
+                    );
+                    width
+                };
+
+                pub const [<$register _BYTE_ORDER>]: $crate::ByteOrder = {
+                    let order = $peripheral_name::BYTE_ORDER;
+                    $(let order = $crate::ByteOrder::$order;)?
+                    order
+                };
+
+                pub const [<$register _PAGE>]: Option<u32> = {
+                    let page: Option<u32> = None;
+                    $(let page = Some($page);)?
+                    page
+                };
+
+                pub const [<$register _PRESENT>]: bool = {
+                    let present = true;
+                    $(let present = $crate::is_member(&[$(stringify!($only)),+], stringify!($peripheral_name));)?
+                    present
+                };
+            )+
+            // An alias shares the address and storage of its canonical register
+            $($(
+                pub const [<$alias _ADDRESS>]: usize = $peripheral_name::[<$canonical _ADDRESS>];
+
+                pub const [<$alias _INDEX>]: usize = $peripheral_name::[<$canonical _INDEX>];
+
+                pub const [<$alias _WIDTH>]: u32 = $peripheral_name::[<$canonical _WIDTH>];
+
+                pub const [<$alias _BYTE_ORDER>]: $crate::ByteOrder = $peripheral_name::[<$canonical _BYTE_ORDER>];
+
+                pub const [<$alias _PAGE>]: Option<u32> = $peripheral_name::[<$canonical _PAGE>];
+
+                pub const [<$alias _PRESENT>]: bool = $peripheral_name::[<$canonical _PRESENT>];
+            )+)?
+            // An overlay is a layout of its register, at the same index
+            $($(
+                pub const [<$overlay _INDEX>]: usize = $peripheral_name::[<$overlay_register _INDEX>];
+            )+)?
         }
 
+        // Memory mapped registers are accessed at their own width, in place
+        $(
+            const _: () = assert!(
+                !$peripheral_name::MEMORY_MAPPED || $crate::mmio::mappable($peripheral_name::[<$register _WIDTH>], $addr, 0),
+                concat!(stringify!($register), " must be 8, 16, 32, 64 or 128 bits wide and aligned to its width to be memory mapped")
+            );
+        )+
+        $($(
+            const _: () = assert!(
+                !$peripheral_name::MEMORY_MAPPED || $crate::mmio::mappable($peripheral_name::[<$array _ARRAY>].width, $array_addr, $array_stride),
+                concat!(stringify!($array), " must be 8, 16, 32, 64 or 128 bits wide and aligned to its width to be memory mapped")
+            );
+        )+)?
+
         impl $peripheral_name {
-            pub fn new() -> Self {
+            /// Byte order of registers that don't give their own
+            pub const BYTE_ORDER: $crate::ByteOrder = {
+                let order = $crate::ByteOrder::BigEndian;
+                $(let order = $crate::ByteOrder::$byte_order;)?
+                order
+            };
+
+            /// Whether the registers are accessed in place, with `storage: MemoryMapped`
+            pub const MEMORY_MAPPED: bool = $crate::peripheral!(@memory_mapped $($storage)?);
+
+            /// Whether addresses count registers or bytes
+            pub const ADDRESSING: $crate::Addressing = {
+                let addressing = $crate::Addressing::Register;
+                $(let addressing = $crate::Addressing::$addressing;)?
+                addressing
+            };
+
+            $crate::peripheral!(@new $backer, $count $(, $storage)?);
+
+            fn with_registers(registers: $crate::peripheral!(@storage $backer, $count $(, $storage)?)) -> Self {
                 let mut address_index: [$address_type; $count] = [0; $count];
 
                 $(
                     address_index[$index as usize] = $addr as $address_type;
                 )+
+                $($(
+                    for i in 0..$array_count {
+                        address_index[$array_index + i] = ($array_addr + i * $array_stride) as $address_type;
+                    }
+                )+)?
 
                 $peripheral_name {
-                    registers: [RegisterBacker { contents: 0 }; $count],
+                    registers,
                     address_index,
                     i2c_addr: $i2c_addr,
+                    device_page: None,
                 }
             }
 
+            fn contents_of(&self, index: usize) -> <$backer as $crate::Backer>::Value {
+                <Self as $crate::Family>::backer(self, index).read().contents()
+            }
+
+            fn set_contents_of(&mut self, index: usize, value: <$backer as $crate::Backer>::Value) {
+                let backer = <Self as $crate::Family>::backer(self, index);
+                // Memory mapped writes reach the device, so leave W1C and W0C fields unchanged
+                let value = if backer.is_memory_mapped() {
+                    let (w1c, w0c) = Self::WRITE_MASKS[index];
+                    (value as u128 & !w1c | w0c) as <$backer as $crate::Backer>::Value
+                } else {
+                    value
+                };
+                backer.write($backer::new(value));
+            }
+
+            /// Index of the register at `address`. In a paged map only registers on the
+            /// page held by the page select register, or without a page, are matched.
             pub fn find_index_by_address(&self, address: usize) -> Option<usize> {
                 for i in 0..$count {
-                    if self.address_index[i] == address as $address_type {
+                    if self.address_index[i] == address as $address_type
+                        && <Self as $crate::Family>::PRESENT[i]
+                        && self.on_selected_page(i)
+                    {
                         return Some(i);
                     }
                 }
@@ -188,11 +830,11 @@ macro_rules! peripheral {
                 None
             }
 
-            pub fn direct_update_by_address(&mut self, address: usize, val: RegisterType) -> &mut Self {
+            pub fn direct_update_by_address(&mut self, address: usize, val: <$backer as $crate::Backer>::Value) -> &mut Self {
                 let index = self.find_index_by_address(address);
                 match index {
                     Some(i) => {
-                        self.registers[i].update(val);
+                        self.set_contents_of(i, val & Self::mask_of(i));
                     },
                     None => {
                         panic!("Address not found");
@@ -201,11 +843,11 @@ macro_rules! peripheral {
                 self
             }
 
-            pub fn direct_read_by_address(&self, address: usize) -> RegisterType {
+            pub fn direct_read_by_address(&self, address: usize) -> <$backer as $crate::Backer>::Value {
                 let index = self.find_index_by_address(address);
                 match index {
                     Some(i) => {
-                        return self.registers[i].contents();
+                        return self.contents_of(i);
                     },
                     None => {
                         panic!("Address not found");
@@ -213,8 +855,8 @@ macro_rules! peripheral {
                 }
             }
 
-            pub fn direct_update_by_index(&mut self, index: usize, val: RegisterType) -> &mut Self {
-                self.registers[index].update(val);
+            pub fn direct_update_by_index(&mut self, index: usize, val: <$backer as $crate::Backer>::Value) -> &mut Self {
+                self.set_contents_of(index, val & Self::mask_of(index));
                 self
             }
 
@@ -233,187 +875,2137 @@ macro_rules! peripheral {
             pub fn get_register_count(&self) -> usize {
                 $count
             }
-        }
-
-        type PeripheralType = $peripheral_name;
-    };
-
-}
 
-/// This macro is used to generate a struct that contains a pointer to a register.
-///
-/// This function requires that a peripheral has been created using the
-/// peripheral! macro so that that pointers to the backing memory can be used.
-#[macro_export]
-macro_rules! register {
-    ($register:ident) => {
-        pub struct $register {
-            register: *mut RegisterBacker,
-        }
+            /// Page of the register at `index`, None if it isn't paged
+            pub fn page_of(index: usize) -> Option<u32> {
+                const PAGES: [Option<u32>; $count] = {
+                    let mut pages = [None; $count];
+                    $(
+                        pages[$index] = $peripheral_name::[<$register _PAGE>];
+                    )+
+                    pages
+                };
+                PAGES[index]
+            }
 
-        impl $register {
-            pub fn contents(&self) -> RegisterType {
-                unsafe { (*self.register).contents }
+            fn on_selected_page(&self, _index: usize) -> bool {
+                let on_page = true;
+                $(
+                    let selected = self.contents_of(Self::[<$page_register _INDEX>]) as u32;
+                    let on_page = !matches!(Self::page_of(_index), Some(page) if page != selected);
+                )?
+                on_page
             }
 
-            pub fn address(&self) -> usize {
-                RegisterAddress::$register as usize
+            /// Width in bits of the register at `index`
+            pub fn width_of(index: usize) -> u32 {
+                const WIDTHS: [u32; $count] = {
+                    let mut widths = [<$backer as $crate::Backer>::WIDTH; $count];
+                    $(
+                        widths[$index] = $peripheral_name::[<$register _WIDTH>];
+                    )+
+                    $($(
+                        let mut i = 0;
+                        while i < $array_count {
+                            widths[$array_index + i] = $peripheral_name::[<$array _ARRAY>].width;
+                            i += 1;
+                        }
+                    )+)?
+                    widths
+                };
+                WIDTHS[index]
             }
 
-            pub fn index(&self) -> usize {
-                RegisterIndex::$register as usize
+            /// Byte order of the register at `index`
+            pub fn byte_order_of(index: usize) -> $crate::ByteOrder {
+                const ORDERS: [$crate::ByteOrder; $count] = {
+                    let mut orders = [$peripheral_name::BYTE_ORDER; $count];
+                    $(
+                        orders[$index] = $peripheral_name::[<$register _BYTE_ORDER>];
+                    )+
+                    $($(
+                        let mut i = 0;
+                        while i < $array_count {
+                            orders[$array_index + i] = $peripheral_name::[<$array _ARRAY>].byte_order;
+                            i += 1;
+                        }
+                    )+)?
+                    orders
+                };
+                ORDERS[index]
             }
 
-            pub fn update(&mut self, val: RegisterType) -> &mut Self {
-                unsafe {
-                    (*self.register).contents = val;
+            /// Loads registers at consecutive addresses, starting at `start_address`, from
+            /// `bytes`, using the width and byte order of each register. The next register
+            /// is at the next address, or after the bytes of this one with
+            /// `addressing: Byte`. Registers before an error are loaded.
+            pub fn load_bytes(&mut self, start_address: usize, bytes: &[u8]) -> Result<&mut Self, $crate::ByteError> {
+                let mut address = start_address;
+                let mut rest = bytes;
+                while !rest.is_empty() {
+                    let index = self.find_index_by_address(address).ok_or($crate::ByteError::UnknownAddress(address))?;
+                    let size = Self::width_of(index) as usize / 8;
+                    if rest.len() < size {
+                        return Err($crate::ByteError::Length);
+                    }
+                    let value = Self::byte_order_of(index).decode(&rest[..size]);
+                    self.set_contents_of(index, value as _);
+                    rest = &rest[size..];
+                    address += Self::address_step(size);
                 }
-                self
+                Ok(self)
             }
 
-            pub fn clear(&mut self) -> &mut Self {
-                unsafe {
-                    (*self.register).clear_all();
+            /// Stores registers at consecutive addresses, starting at `start_address`, into
+            /// `bytes`, using the width and byte order of each register. Addresses step as
+            /// in `load_bytes`.
+            pub fn store_bytes(&self, start_address: usize, bytes: &mut [u8]) -> Result<(), $crate::ByteError> {
+                let mut address = start_address;
+                let mut rest = bytes;
+                while !rest.is_empty() {
+                    let index = self.find_index_by_address(address).ok_or($crate::ByteError::UnknownAddress(address))?;
+                    let size = Self::width_of(index) as usize / 8;
+                    if rest.len() < size {
+                        return Err($crate::ByteError::Length);
+                    }
+                    let (register, remaining) = rest.split_at_mut(size);
+                    Self::byte_order_of(index).encode(self.contents_of(index) as u128, register);
+                    rest = remaining;
+                    address += Self::address_step(size);
                 }
-                self
+                Ok(())
             }
-        }
 
-        impl PeripheralType {
-            pub fn $register(&self) -> $register {
-                $register {
-                    register: &self.registers[RegisterIndex::$register as usize] as *const _
-                        as *mut RegisterBacker,
+            /// Addresses taken by a register of `size` bytes
+            fn address_step(size: usize) -> usize {
+                match Self::ADDRESSING {
+                    $crate::Addressing::Register => 1,
+                    $crate::Addressing::Byte => size,
                 }
             }
-        }
-    };
-}
 
-/// This macro generates a bitfield within a register for a single bit. For example,
-/// if a register contains a bit flag, this could be used to get / set the bit
-/// flag in a named way.
-#[macro_export]
-macro_rules! bitfield {
-    ($register:ident, $bitfield_name:ident, $bit:literal) => {
-        paste! {
-            pub trait [<$register _ $bitfield_name>]{
-                fn [<get_ $bitfield_name>](&self) -> bool;
-                fn [<set_ $bitfield_name>](&mut self, value: bool) -> &mut Self;
+            /// Mask of the bits the register at `index` holds
+            pub fn mask_of(index: usize) -> <$backer as $crate::Backer>::Value {
+                (u128::MAX >> (u128::BITS - Self::width_of(index))) as <$backer as $crate::Backer>::Value
             }
 
-            impl [<$register _ $bitfield_name>] for $register {
-                fn [<get_ $bitfield_name>](&self) -> bool {
-                    unsafe {
-                        self.register.as_mut().unwrap().is_set($bit)
+            /// The W1C and W0C masks of the register at each index, from the access fields
+            /// of its register!, register_array! and register_overlay! structs
+            pub const WRITE_MASKS: [(u128, u128); $count] = {
+                let mut masks = [(0, 0); $count];
+                $(
+                    let (w1c, w0c) = $family::[<$register _WRITE_MASKS>];
+                    masks[$index] = (masks[$index].0 | w1c, masks[$index].1 | w0c);
+                )+
+                $($(
+                    let index = $peripheral_name::[<$alias _INDEX>];
+                    let (w1c, w0c) = $family::[<$alias _WRITE_MASKS>];
+                    masks[index] = (masks[index].0 | w1c, masks[index].1 | w0c);
+                )+)?
+                $($(
+                    let index = $peripheral_name::[<$overlay _INDEX>];
+                    let (w1c, w0c) = $family::[<$overlay _WRITE_MASKS>];
+                    masks[index] = (masks[index].0 | w1c, masks[index].1 | w0c);
+                )+)?
+                $($(
+                    let (w1c, w0c) = $family::[<$array _WRITE_MASKS>];
+                    let mut i = 0;
+                    while i < $array_count {
+                        let index = $array_index + i;
+                        masks[index] = (masks[index].0 | w1c, masks[index].1 | w0c);
+                        i += 1;
                     }
-                }
+                )+)?
+                masks
+            };
+
+            /// Contents of the register at `index` to write to the device. W1C fields are
+            /// written as 0 and W0C fields as 1, which leaves them unchanged on the device.
+            pub fn write_value(&self, index: usize) -> <$backer as $crate::Backer>::Value {
+                let (w1c, w0c) = Self::WRITE_MASKS[index];
+                (self.contents_of(index) as u128 & !w1c | w0c) as <$backer as $crate::Backer>::Value
+            }
 
-                fn [<set_ $bitfield_name>](&mut self, value: bool) -> &mut Self {
-                    unsafe {
-                        if value {
-                            self.register.as_mut().unwrap().set_bit($bit);
-                        } else {
-                            self.register.as_mut().unwrap().clear_bit($bit);
-                        }
+            /// Acknowledges `field` of `register`, such as an interrupt flag, clearing it in
+            /// the peripheral. A W1C field is cleared on the device by writing 1 to it alone,
+            /// a W0C field by writing 0 to it alone, and a read / write field by writing the
+            /// register back. RC fields were already cleared on the device when they were
+            /// read, and RS fields set, so only the peripheral is updated.
+            ///
+            /// Panics if `field` wasn't declared with an access on `register`.
+            pub fn acknowledge<B: $crate::bus::RegisterBus, R: $crate::RegisterFields>(&mut self, bus: &mut B, register: &R, field: $crate::FieldInfo) -> Result<(), B::Error> {
+                assert!(R::has_field(&field), "The field must be declared with an access on the register");
+                let index = register.index();
+                let contents = self.contents_of(index) as u128;
+                let contents = match field.access {
+                    $crate::Access::RS => contents | field.mask(),
+                    _ => contents & !field.mask(),
+                };
+                self.set_contents_of(index, contents as _);
+
+                let value = match field.access {
+                    $crate::Access::RC | $crate::Access::RS => return Ok(()),
+                    $crate::Access::W1C => self.write_value(index) as u128 | field.mask(),
+                    $crate::Access::W0C => self.write_value(index) as u128 & !field.mask(),
+                    $crate::Access::ReadWrite => self.write_value(index) as u128,
+                };
+                let address = self.bus_address(bus, index)?;
+                let mut bytes = [0; 16];
+                let bytes = &mut bytes[..Self::width_of(index) as usize / 8];
+                Self::byte_order_of(index).encode(value, bytes);
+                bus.write(self.i2c_addr, address, bytes)
+            }
+
+            /// Page last selected on the device by bus_read / bus_write, None if unknown
+            pub fn get_device_page(&self) -> Option<u32> {
+                self.device_page
+            }
+
+            /// Forgets the page selected on the device, for example after it was reset,
+            /// so that the next access to a paged register selects the page again.
+            pub fn invalidate_page(&mut self) -> &mut Self {
+                self.device_page = None;
+                self
+            }
+
+            /// Writes the page select register if the device isn't already on `page`.
+            /// Does nothing for peripherals without a page_select register.
+            pub fn select_page<B: $crate::bus::RegisterBus>(&mut self, bus: &mut B, page: u32) -> Result<(), B::Error> {
+                $(
+                    if self.device_page != Some(page) {
+                        let index = Self::[<$page_register _INDEX>];
+                        self.set_contents_of(index, page as _);
+                        let mut bytes = [0; 16];
+                        let bytes = &mut bytes[..Self::width_of(index) as usize / 8];
+                        Self::byte_order_of(index).encode(page as u128, bytes);
+                        bus.write(self.i2c_addr, Self::[<$page_register _ADDRESS>], bytes)?;
+                        self.device_page = Some(page);
                     }
+                )?
+                let _ = (bus, page);
+                Ok(())
+            }
 
-                    self
+            fn bus_address<B: $crate::bus::RegisterBus>(&mut self, bus: &mut B, index: usize) -> Result<usize, B::Error> {
+                if let Some(page) = Self::page_of(index) {
+                    self.select_page(bus, page)?;
                 }
+                Ok(self.address_index[index] as usize)
             }
-        }
-    };
-}
 
-/// Generates an enumerated type for a bitrange and should be done BEFORE the
-/// bitrange register is used to ensure the correct types are in place. This macro
-/// also takes care of converting the enum to a number and vice versa.
-#[macro_export]
-macro_rules! bitrange_enum_values {
-    ($enum_name:ident, $enum_type:ty, [$(($name:ident, $value:literal)),+]) => {
-        paste! {
-            #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-            pub enum $enum_name {
+            fn track_page(&mut self, _index: usize) {
                 $(
-                    $name = $value,
-                )+
+                    if _index == Self::[<$page_register _INDEX>] {
+                        self.device_page = Some(self.contents_of(_index) as u32);
+                    }
+                )?
             }
 
-            pub fn [<$enum_name FromNum>](num: $enum_type) -> Option<$enum_name> {
-                match num {
-                    $(
-                        $value => Some($enum_name::$name),
-                    )+
-                    _ => None,
-                }
+            /// Reads the register at `index` from the device into the peripheral,
+            /// selecting its page first if needed.
+            pub fn bus_read<B: $crate::bus::RegisterBus>(&mut self, bus: &mut B, index: usize) -> Result<<$backer as $crate::Backer>::Value, B::Error> {
+                let address = self.bus_address(bus, index)?;
+                let mut bytes = [0; 16];
+                let bytes = &mut bytes[..Self::width_of(index) as usize / 8];
+                bus.read(self.i2c_addr, address, bytes)?;
+                let value = Self::byte_order_of(index).decode(bytes) as <$backer as $crate::Backer>::Value;
+                self.set_contents_of(index, value);
+                self.track_page(index);
+                Ok(value)
             }
 
-            pub fn [<$enum_name ToNum>](enum_value: $enum_name) -> $enum_type {
-                enum_value as $enum_type
+            /// Writes the register at `index` from the peripheral to the device,
+            /// selecting its page first if needed.
+            pub fn bus_write<B: $crate::bus::RegisterBus>(&mut self, bus: &mut B, index: usize) -> Result<(), B::Error> {
+                let address = self.bus_address(bus, index)?;
+                let mut bytes = [0; 16];
+                let bytes = &mut bytes[..Self::width_of(index) as usize / 8];
+                Self::byte_order_of(index).encode(self.write_value(index) as u128, bytes);
+                bus.write(self.i2c_addr, address, bytes)?;
+                self.track_page(index);
+                Ok(())
             }
         }
-    };
-}
 
-/// Defines a bitrange and the correct getters and setters for the bitrange using
-/// an enum defined using the bitrange_enum_values macro. This helps enforce
-/// only valid enum can be used for the getters and setters. If a register
-/// doesn't have an enum that is easily mappable, consider bitrange_raw instead.
-#[macro_export]
-macro_rules! bitrange {
-    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty) => {
-        paste! {
-            pub trait [<$register _ $bitrange_name>] {
-                fn [<get_ $bitrange_name>](&self) -> Option<$val_type>;
-                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self;
+        $($(
+            // The literals can read as `x + 1 <= y` to clippy
+            #[allow(clippy::int_plus_one)]
+            const _: () = assert!(
+                $array_index + $array_count <= $count,
+                concat!(stringify!($array), " has more elements than ", stringify!($peripheral_name), " has registers")
+            );
+
+            impl $peripheral_name {
+                #[allow(non_upper_case_globals)]
+                pub const [<$array _ARRAY>]: $crate::RegisterArray = $crate::RegisterArray {
+                    name: stringify!($array),
+                    address: $array_addr,
+                    stride: $array_stride,
+                    count: $array_count,
+                    index: $array_index,
+                    width: {
+                        let width = <$backer as $crate::Backer>::WIDTH;
+                        $(let width = $array_width;)?
+                        assert!(
+                            width <= <$backer as $crate::Backer>::WIDTH && width % 8 == 0,
+                            concat!(stringify!($array), " must be a whole number of bytes no wider than ", stringify!($backer))
+                        );
+                        width
+                    },
+                    byte_order: {
+                        let order = $peripheral_name::BYTE_ORDER;
+                        $(let order = $crate::ByteOrder::$array_order;)?
+                        order
+                    },
+                };
             }
-
-            impl [<$register _ $bitrange_name>] for $register {
-                fn [<get_ $bitrange_name>](&self) -> Option<$val_type> {
-                    unsafe {
-                        let val = self.register.as_mut().unwrap().get_range(BitRange {stop_bit: $msb, start_bit: $lsb });
-                        [<$val_type FromNum>](val)
-                    }
+        )+)?
+
+        // Array elements take the indexes from their base index on, which must not be
+        // used by a named register or another array
+        const _: () = {
+            let named: &[usize] = &[$($index),+];
+            let arrays: &[(usize, usize)] = &[$($(($array_index, $array_count)),+)?];
+            let mut a = 0;
+            while a < arrays.len() {
+                let (start, count) = arrays[a];
+                let mut i = 0;
+                while i < named.len() {
+                    assert!(
+                        named[i] < start || named[i] >= start + count,
+                        concat!("an array of ", stringify!($peripheral_name), " shares an index with a register")
+                    );
+                    i += 1;
                 }
+                let mut b = a + 1;
+                while b < arrays.len() {
+                    assert!(
+                        arrays[b].0 >= start + count || arrays[b].0 + arrays[b].1 <= start,
+                        concat!("two arrays of ", stringify!($peripheral_name), " share an index")
+                    );
+                    b += 1;
+                }
+                a += 1;
+            }
+        };
 
-                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self {
-                    unsafe {
-                        self.register.as_mut().unwrap().set_range(BitRange { stop_bit: $msb, start_bit: $lsb }, [<$val_type ToNum>](value));
-                    }
+        impl $crate::Family for $peripheral_name {
+            type Base = $family;
 
-                    self
-                }
+            const PRESENT: &'static [bool] = &{
+                let mut present = [true; $count];
+                $(
+                    present[$index] = $peripheral_name::[<$register _PRESENT>];
+                )+
+                present
+            };
+
+            fn backer(&self, index: usize) -> $crate::mmio::BackerPtr<$backer> {
+                $crate::mmio::Storage::<$backer>::backer(&self.registers, index, self.address_index[index] as usize, Self::width_of(index))
             }
         }
-    };
-}
 
-/// Defines a bitrange and the correct getters and setters for the bitrange using
-/// a raw type, such as u8, u16, u32, etc. This can be used for registers like
-/// ID and Revision that don't map to an enum but occupy a bitrange.
-#[macro_export]
-macro_rules! bitrange_raw {
-    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty) => {
-        paste! {
+        impl $crate::Peripheral for $peripheral_name {
+            type Backer = $backer;
+
+            fn registers(&self) -> &'static [$crate::RegisterInfo] {
+                const LEN: usize = 0 $(+ $peripheral_name::[<$register _PRESENT>] as usize)+ $($(+ $array_count)+)?;
+                const REGISTERS: [$crate::RegisterInfo; LEN] = {
+                    let empty = $crate::RegisterInfo { name: "", address: 0, index: 0, width: 0, page: None, element: None };
+                    let mut table = [empty; LEN];
+                    let mut n = 0;
+                    $(
+                        if $peripheral_name::[<$register _PRESENT>] {
+                            table[n] = $crate::RegisterInfo {
+                                name: stringify!($register),
+                                address: $addr,
+                                index: $index,
+                                width: $peripheral_name::[<$register _WIDTH>],
+                                page: $peripheral_name::[<$register _PAGE>],
+                                element: None,
+                            };
+                            n += 1;
+                        }
+                    )+
+                    // The elements of an array are listed together, under the name of the array
+                    $($(
+                        let mut i = 0;
+                        while i < $array_count {
+                            table[n] = $crate::RegisterInfo {
+                                name: stringify!($array),
+                                address: $array_addr + i * $array_stride,
+                                index: $array_index + i,
+                                width: $peripheral_name::[<$array _ARRAY>].width,
+                                page: None,
+                                element: Some(i),
+                            };
+                            n += 1;
+                            i += 1;
+                        }
+                    )+)?
+                    let _ = n;
+                    table
+                };
+                &REGISTERS
+            }
+
+            $(
+            fn fields(&self, index: usize) -> &'static [$crate::FieldInfo] {
+                // The fields of every register struct sharing an index, such as an alias
+                // or an overlay, are listed together, grouped by index
+                const LEN: usize = {
+                    let mut len = 0;
+                    let mut i = 0;
+                    while i < $count {
+                        $(
+                            if $field_register::owns_index(i) {
+                                len += [$(stringify!($field)),+].len();
+                            }
+                        )+
+                        i += 1;
+                    }
+                    len
+                };
+                static FIELDS: ([$crate::FieldInfo; LEN], [usize; $count + 1]) = {
+                    let empty = $crate::FieldInfo { name: "", msb: 0, lsb: 0, signed: false, access: $crate::Access::ReadWrite, slices: &[] };
+                    let mut fields = [empty; LEN];
+                    let mut starts = [0; $count + 1];
+                    let mut n = 0;
+                    let mut i = 0;
+                    while i < $count {
+                        starts[i] = n;
+                        $(
+                            if $field_register::owns_index(i) {
+                                let group = [$($field_register::$field),+];
+                                let mut j = 0;
+                                while j < group.len() {
+                                    fields[n] = group[j];
+                                    n += 1;
+                                    j += 1;
+                                }
+                            }
+                        )+
+                        i += 1;
+                    }
+                    starts[$count] = n;
+                    (fields, starts)
+                };
+                &FIELDS.0[FIELDS.1[index]..FIELDS.1[index + 1]]
+            }
+            )?
+
+            fn read_register(&self, index: usize) -> u128 {
+                self.contents_of(index) as u128
+            }
+
+            fn write_register(&mut self, index: usize, value: u128) {
+                self.set_contents_of(index, value as <$backer as $crate::Backer>::Value & Self::mask_of(index));
+            }
+        }
+        }
+    };
+    ($peripheral_name:ident, $address_type:ty, $i2c_addr:literal, $count:literal, [$(($register:ident, $addr:literal, $index:literal $($options:tt)*)),+] $(, $section:ident: $value:tt)*) => {
+        $crate::peripheral!($peripheral_name: RegisterBacker, $address_type, $i2c_addr, $count, [$(($register, $addr, $index $($options)*)),+] $(, $section: $value)*);
+        $($crate::peripheral!(@legacy $section: $value);)*
+
+        // Paged registers can share an address, so these are constants rather than an enum
+        pub struct RegisterAddress;
+
+        #[allow(non_upper_case_globals)]
+        impl RegisterAddress {
+            $(
+                pub const $register: usize = $addr;
+            )+
+        }
+
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum RegisterIndex {
+            $(
+                $register = $index,
+            )+
+        }
+
+        type PeripheralType = $peripheral_name;
+    };
+
+}
+
+/// This macro is used to generate a struct that contains a pointer to a register.
+///
+/// This function requires that a peripheral has been created using the
+/// peripheral! macro so that that pointers to the backing memory can be used.
+///
+/// `contents()` and `update()` use RegisterType, or the type given after the name
+/// for registers narrower than RegisterType, such as `register!(Config, u8)`.
+///
+/// The register of a named peripheral is given as `register!(Max17261::Status)`, which
+/// generates a struct named `Max17261Status`, or `register!(Max17261::Status as Status)`
+/// to choose the name.
+///
+/// The accessor is a method of the peripheral, and of each of its variants through the
+/// `<Name>Access` trait, such as `StatusAccess`, which generic code over
+/// `P: Family<Base = Max17261>` imports.
+///
+/// Fields declared with an access, such as the W1C flags of `bitfield!(Status, Fault, 0, W1C)`,
+/// are listed by name in `access: [...]`, as `register!(Status, access: [Fault])`, which
+/// the register collects in its `W1C_MASK` and `W0C_MASK`.
+///
+/// A variant that lays a register out differently lists its own register in the map,
+/// and `overrides:` gives that variant an accessor of the same name as the register of
+/// the family, `register!(Max17261::Config2 as Max17260Config, overrides: [Max17260::Config])`,
+/// so `max17260.Config()` returns a `Max17260Config`. Generic code still uses the layout of
+/// the family.
+#[macro_export]
+macro_rules! register {
+    (@struct $peripheral:ident::$register:ident as $name:ident, $reg_type:ty, [$($field:ident),*]) => {
+        $crate::paste::paste! {
+            pub struct $name {
+                register: $crate::mmio::BackerPtr<<$peripheral as $crate::Peripheral>::Backer>,
+            }
+
+            const _: () = assert!(
+                $peripheral::[<$register _WIDTH>] <= <$reg_type>::BITS,
+                concat!(
+                    stringify!($register),
+                    " is wider than ",
+                    stringify!($reg_type)
+                )
+            );
+
+            impl $name {
+                /// Width of the register in bits
+                pub const WIDTH: u32 = $peripheral::[<$register _WIDTH>];
+
+                pub fn contents(&self) -> $reg_type {
+                    self.register.read().contents() as $reg_type
+                }
+
+                pub fn address(&self) -> usize {
+                    $peripheral::[<$register _ADDRESS>]
+                }
+
+                pub fn index(&self) -> usize {
+                    $peripheral::[<$register _INDEX>]
+                }
+
+                /// True if the backing register at `index` belongs to this register
+                pub const fn owns_index(index: usize) -> bool {
+                    index == $peripheral::[<$register _INDEX>]
+                }
+
+                pub fn update(&mut self, val: $reg_type) -> &mut Self {
+                    self.modify(|register| { register.update((val & $peripheral::mask_of(self.index()) as $reg_type) as _); });
+                    self
+                }
+
+                /// Contents of the register in its byte order
+                pub fn to_bytes(&self) -> [u8; $peripheral::[<$register _WIDTH>] as usize / 8] {
+                    let mut bytes = [0; $peripheral::[<$register _WIDTH>] as usize / 8];
+                    $peripheral::[<$register _BYTE_ORDER>]
+                        .encode(self.register.read().contents() as u128, &mut bytes);
+                    bytes
+                }
+
+                /// Sets the contents of the register from bytes in its byte order
+                pub fn from_bytes(
+                    &mut self,
+                    bytes: [u8; $peripheral::[<$register _WIDTH>] as usize / 8],
+                ) -> &mut Self {
+                    self.modify(|register| { register.update($peripheral::[<$register _BYTE_ORDER>].decode(&bytes) as _); });
+                    self
+                }
+
+                pub fn clear(&mut self) -> &mut Self {
+                    self.modify(|register| { register.clear_all(); });
+                    self
+                }
+            }
+        }
+
+        $crate::register!(@access $peripheral::$register as $name, [$($field),*]);
+    };
+    (@access $peripheral:ident::$register:ident as $name:ident, [$($field:ident),*]) => {
+        $crate::paste::paste! {
+            impl $name {
+                /// The fields listed in `access: [...]`, the fields declared with an access
+                pub const ACCESS_FIELDS: &'static [$crate::FieldInfo] = &[$($name::$field),*];
+
+                /// Bits of the W1C fields, written as 0 so that writing back the register
+                /// doesn't clear them
+                pub const W1C_MASK: u128 = $crate::FieldInfo::access_mask(Self::ACCESS_FIELDS, $crate::Access::W1C);
+
+                /// Bits of the W0C fields, written as 1 so that writing back the register
+                /// doesn't clear them
+                pub const W0C_MASK: u128 = $crate::FieldInfo::access_mask(Self::ACCESS_FIELDS, $crate::Access::W0C);
+
+                // Writes to a memory mapped register reach the device, so W1C and W0C
+                // fields, of this register and of any other sharing its index, are
+                // written so as to leave them unchanged, as bus_write does
+                fn modify(&self, f: impl FnOnce(&mut <$peripheral as $crate::Peripheral>::Backer)) {
+                    self.register.modify(|register| {
+                        f(register);
+                        if self.register.is_memory_mapped() {
+                            let (w1c, w0c) = $peripheral::WRITE_MASKS[<Self as $crate::RegisterFields>::index(self)];
+                            let contents = register.contents() as u128 & !(Self::W1C_MASK | w1c) | Self::W0C_MASK | w0c;
+                            register.update(contents as _);
+                        }
+                    });
+                }
+
+                /// Acknowledges `field`, such as an interrupt flag. A memory mapped W1C field
+                /// is cleared by writing 1 to it alone and a W0C field by writing 0 to it
+                /// alone, while RC and RS fields were already changed by reading them. A
+                /// cached register is only updated, the peripheral's acknowledge also writes
+                /// the device.
+                ///
+                /// Panics if `field` wasn't declared with an access on this register.
+                pub fn acknowledge(&mut self, field: $crate::FieldInfo) -> &mut Self {
+                    assert!(
+                        <Self as $crate::RegisterFields>::has_field(&field),
+                        "The field must be declared with an access on the register"
+                    );
+                    let contents = self.register.read().contents() as u128;
+                    if self.register.is_memory_mapped() {
+                        let (w1c, w0c) = $peripheral::WRITE_MASKS[<Self as $crate::RegisterFields>::index(self)];
+                        let unchanged = contents & !(Self::W1C_MASK | w1c) | Self::W0C_MASK | w0c;
+                        let contents = match field.access {
+                            $crate::Access::W1C => unchanged | field.mask(),
+                            $crate::Access::W0C => unchanged & !field.mask(),
+                            $crate::Access::ReadWrite => unchanged & !field.mask(),
+                            $crate::Access::RC | $crate::Access::RS => return self,
+                        };
+                        self.register.write(<<$peripheral as $crate::Peripheral>::Backer>::new(contents as _));
+                    } else {
+                        let contents = match field.access {
+                            $crate::Access::RS => contents | field.mask(),
+                            _ => contents & !field.mask(),
+                        };
+                        self.register.write(<<$peripheral as $crate::Peripheral>::Backer>::new(contents as _));
+                    }
+                    self
+                }
+            }
+
+            impl $crate::RegisterFields for $name {
+                fn index(&self) -> usize {
+                    $name::index(self)
+                }
+
+                fn has_field(field: &$crate::FieldInfo) -> bool {
+                    // Bits of the fields with each access, in the order of Access
+                    const MASKS: [u128; 5] = [
+                        $crate::FieldInfo::access_mask($name::ACCESS_FIELDS, $crate::Access::ReadWrite),
+                        $name::W1C_MASK,
+                        $name::W0C_MASK,
+                        $crate::FieldInfo::access_mask($name::ACCESS_FIELDS, $crate::Access::RC),
+                        $crate::FieldInfo::access_mask($name::ACCESS_FIELDS, $crate::Access::RS),
+                    ];
+                    let mask = field.mask();
+                    mask != 0 && MASKS[field.access as usize] & mask == mask
+                }
+            }
+        }
+    };
+    // The options without a type use the type of the backer
+    ($peripheral:ident::$register:ident as $name:ident $(, $option:ident: $value:tt)*) => {
+        $crate::register!($peripheral::$register as $name, <<$peripheral as $crate::Peripheral>::Backer as $crate::Backer>::Value $(, $option: $value)*);
+    };
+    ($peripheral:ident::$register:ident as $name:ident, $reg_type:ty $(, access: [$($field:ident),+])? $(, overrides: [$($variant:ident::$accessor:ident),+])?) => {
+        $crate::register!(@struct $peripheral::$register as $name, $reg_type, [$($($field),+)?]);
+
+        $crate::paste::paste! {
+            pub trait [<$name Access>] {
+                #[allow(non_snake_case)]
+                fn $register(&self) -> $name;
+            }
+
+            impl<P: $crate::Family<Base = $peripheral>> [<$name Access>] for P {
+                fn $register(&self) -> $name {
+                    const {
+                        assert!(
+                            P::PRESENT[$peripheral::[<$register _INDEX>]],
+                            concat!(stringify!($register), " isn't a register of this variant")
+                        )
+                    };
+                    $name {
+                        register: self.backer($peripheral::[<$register _INDEX>]),
+                    }
+                }
+            }
+
+            // The base peripheral also has the accessor without importing the trait.
+            // Inline keeps it from being compiled, and failing the PRESENT assertion,
+            // unless it is used
+            impl $peripheral {
+                #[allow(non_snake_case)]
+                #[inline]
+                pub fn $register(&self) -> $name {
+                    <Self as [<$name Access>]>::$register(self)
+                }
+
+                #[doc(hidden)]
+                pub const [<$register _WRITE_MASKS>]: (u128, u128) = ($name::W1C_MASK, $name::W0C_MASK);
+            }
+        }
+
+        $($crate::paste::paste! {
+            // Inherent methods are found before the trait accessor of the family
+            $(
+                impl $variant {
+                    #[allow(non_snake_case)]
+                    #[inline]
+                    pub fn $accessor(&self) -> $name {
+                        <Self as [<$name Access>]>::$register(self)
+                    }
+                }
+            )+
+        })?
+    };
+    ($peripheral:ident::$register:ident $($rest:tt)*) => {
+        $crate::paste::paste! {
+            $crate::register!($peripheral::$register as [<$peripheral $register>] $($rest)*);
+        }
+    };
+    ($register:ident $($rest:tt)*) => {
+        $crate::register!(PeripheralType::$register as $register $($rest)*);
+    };
+}
+
+/// Generates a register struct for a register array declared in the `arrays:`
+/// section of peripheral!. Field macros are used on the array name as for any other
+/// register, and every element shares that field layout.
+///
+/// The peripheral gets an accessor taking the element, `QRTable(i)`, which returns
+/// None if `i` is out of range, and `QRTable_iter()` to iterate over every element.
+///
+/// As with register!, the array of a named peripheral is given as
+/// `register_array!(Max17261::QRTable)`, generating `Max17261QRTable`, or
+/// `register_array!(Max17261::QRTable as QRTable)`, and fields with an access are listed
+/// in `access: [...]`.
+#[macro_export]
+macro_rules! register_array {
+    ($peripheral:ident::$register:ident as $name:ident, access: $fields:tt) => {
+        $crate::register_array!($peripheral::$register as $name, <<$peripheral as $crate::Peripheral>::Backer as $crate::Backer>::Value, access: $fields);
+    };
+    ($peripheral:ident::$register:ident as $name:ident, $reg_type:ty $(, access: [$($field:ident),+])?) => {
+        $crate::paste::paste! {
+            pub struct $name {
+                register: $crate::mmio::BackerPtr<<$peripheral as $crate::Peripheral>::Backer>,
+                element: usize,
+            }
+
+            const _: () = assert!(
+                $peripheral::[<$register _ARRAY>].width <= <$reg_type>::BITS,
+                concat!(stringify!($register), " is wider than ", stringify!($reg_type))
+            );
+
+            impl $name {
+                /// Width of each element in bits
+                pub const WIDTH: u32 = $peripheral::[<$register _ARRAY>].width;
+
+                pub fn contents(&self) -> $reg_type {
+                    self.register.read().contents() as $reg_type
+                }
+
+                /// Position of this register in the array
+                pub fn element(&self) -> usize {
+                    self.element
+                }
+
+                pub fn address(&self) -> usize {
+                    let array = $peripheral::[<$register _ARRAY>];
+                    array.address + self.element * array.stride
+                }
+
+                pub fn index(&self) -> usize {
+                    $peripheral::[<$register _ARRAY>].index + self.element
+                }
+
+                /// True if the backing register at `index` belongs to this array
+                pub const fn owns_index(index: usize) -> bool {
+                    let array = $peripheral::[<$register _ARRAY>];
+                    index >= array.index && index < array.index + array.count
+                }
+
+                pub fn update(&mut self, val: $reg_type) -> &mut Self {
+                    self.modify(|register| { register.update((val & $peripheral::mask_of(self.index()) as $reg_type) as _); });
+                    self
+                }
+
+                /// Contents of the register in its byte order
+                pub fn to_bytes(&self) -> [u8; Self::WIDTH as usize / 8] {
+                    let mut bytes = [0; Self::WIDTH as usize / 8];
+                    $peripheral::[<$register _ARRAY>].byte_order.encode(self.register.read().contents() as u128, &mut bytes);
+                    bytes
+                }
+
+                /// Sets the contents of the register from bytes in its byte order
+                pub fn from_bytes(&mut self, bytes: [u8; Self::WIDTH as usize / 8]) -> &mut Self {
+                    self.modify(|register| { register.update($peripheral::[<$register _ARRAY>].byte_order.decode(&bytes) as _); });
+                    self
+                }
+
+                pub fn clear(&mut self) -> &mut Self {
+                    self.modify(|register| { register.clear_all(); });
+                    self
+                }
+            }
+
+            #[allow(non_snake_case)]
+            pub trait [<$name Access>] {
+                fn $register(&self, element: usize) -> Option<$name>;
+
+                fn [<$register _iter>](&self) -> impl Iterator<Item = $name> + '_;
+            }
+
+            impl<P: $crate::Family<Base = $peripheral>> [<$name Access>] for P {
+                fn $register(&self, element: usize) -> Option<$name> {
+                    let array = $peripheral::[<$register _ARRAY>];
+                    if element >= array.count {
+                        return None;
+                    }
+                    Some($name {
+                        register: self.backer(array.index + element),
+                        element,
+                    })
+                }
+
+                fn [<$register _iter>](&self) -> impl Iterator<Item = $name> + '_ {
+                    (0..$peripheral::[<$register _ARRAY>].count).filter_map(move |i| self.$register(i))
+                }
+            }
+
+            #[allow(non_snake_case)]
+            impl $peripheral {
+                pub fn $register(&self, element: usize) -> Option<$name> {
+                    <Self as [<$name Access>]>::$register(self, element)
+                }
+
+                pub fn [<$register _iter>](&self) -> impl Iterator<Item = $name> + '_ {
+                    <Self as [<$name Access>]>::[<$register _iter>](self)
+                }
+
+                #[doc(hidden)]
+                pub const [<$register _WRITE_MASKS>]: (u128, u128) = ($name::W1C_MASK, $name::W0C_MASK);
+            }
+        }
+
+        $crate::register!(@access $peripheral::$register as $name, [$($($field),+)?]);
+    };
+    ($peripheral:ident::$register:ident as $name:ident) => {
+        $crate::register_array!($peripheral::$register as $name, <<$peripheral as $crate::Peripheral>::Backer as $crate::Backer>::Value);
+    };
+    ($peripheral:ident::$register:ident $($rest:tt)*) => {
+        $crate::paste::paste! {
+            $crate::register_array!($peripheral::$register as [<$peripheral $register>] $($rest)*);
+        }
+    };
+    ($register:ident $($rest:tt)*) => {
+        $crate::register_array!(PeripheralType::$register as $register $($rest)*);
+    };
+}
+
+/// Generates an alternative layout of a register whose meaning depends on a mode field,
+/// such as a threshold register that holds a voltage or a current depending on a bit of
+/// a config register. The overlay is a register struct of its own, so field macros are
+/// used on it as for any other register, and it shares the contents of the register.
+///
+/// The condition is a closure taking the peripheral, and the accessor, named after the
+/// overlay, returns None when the condition is false. Any number of overlays can be
+/// declared for one register, which can also be used through register! directly.
+///
+/// ```ignore
+/// register_overlay!(Threshold as VoltageThreshold, |device| !device.Config().get_current_mode());
+/// register_overlay!(Threshold as CurrentThreshold, |device| device.Config().get_current_mode());
+/// bitrange_quantized!(VoltageThreshold, volts, 11, 0, u16, 0.002);
+/// let volts = device.VoltageThreshold().map(|threshold| threshold.get_volts());
+/// ```
+///
+/// As with register!, the register of a named peripheral is given as
+/// `register_overlay!(Max17261::Threshold as VoltageThreshold, ...)`. A narrower type can
+/// follow the condition, and then the `access: [...]` list of the overlay.
+///
+/// The overlay must be declared with its register in the `overlays` section of
+/// peripheral!, so that the peripheral masks its access fields with those of the register.
+#[macro_export]
+macro_rules! register_overlay {
+    ($peripheral:ident::$register:ident as $name:ident, $condition:expr, access: $fields:tt) => {
+        $crate::register_overlay!($peripheral::$register as $name, $condition, <<$peripheral as $crate::Peripheral>::Backer as $crate::Backer>::Value, access: $fields);
+    };
+    ($peripheral:ident::$register:ident as $name:ident, $condition:expr, $reg_type:ty $(, access: [$($field:ident),+])?) => {
+        $crate::register!(@struct $peripheral::$register as $name, $reg_type, [$($($field),+)?]);
+
+        $crate::paste::paste! {
+            pub trait [<$name Access>] {
+                /// The register in this layout, None if the layout isn't active
+                #[allow(non_snake_case)]
+                fn $name(&self) -> Option<$name>;
+            }
+
+            impl<P: $crate::Family<Base = $peripheral>> [<$name Access>] for P {
+                fn $name(&self) -> Option<$name> {
+                    const {
+                        assert!(
+                            P::PRESENT[$peripheral::[<$register _INDEX>]],
+                            concat!(stringify!($register), " isn't a register of this variant")
+                        )
+                    };
+                    let active: fn(&P) -> bool = $condition;
+                    if !active(self) {
+                        return None;
+                    }
+                    Some($name {
+                        register: self.backer($peripheral::[<$register _INDEX>]),
+                    })
+                }
+            }
+
+            // As with register!, inline so that the accessor is only compiled when used
+            impl $peripheral {
+                #[allow(non_snake_case)]
+                #[inline]
+                pub fn $name(&self) -> Option<$name> {
+                    <Self as [<$name Access>]>::$name(self)
+                }
+
+                #[doc(hidden)]
+                pub const [<$name _WRITE_MASKS>]: (u128, u128) = ($name::W1C_MASK, $name::W0C_MASK);
+            }
+
+            // The peripheral masks the access fields of the overlays it declares
+            const _: () = assert!(
+                $peripheral::[<$name _INDEX>] == $peripheral::[<$register _INDEX>],
+                concat!(stringify!($name), " must be declared as an overlay of ", stringify!($register), " in the overlays section of peripheral!")
+            );
+        }
+    };
+    ($peripheral:ident::$register:ident as $name:ident, $condition:expr) => {
+        $crate::register_overlay!($peripheral::$register as $name, $condition, <<$peripheral as $crate::Peripheral>::Backer as $crate::Backer>::Value);
+    };
+    ($register:ident as $name:ident, $($rest:tt)*) => {
+        $crate::register_overlay!(PeripheralType::$register as $name, $($rest)*);
+    };
+}
+
+/// The FieldInfo of a field, as the constant `Register::name`, and the check that the
+/// field is inside of the register. Used by the field macros. A scattered field gives
+/// its slices instead of its msb and lsb.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! field_info {
+    ($register:ident, $name:ident, slices: [$(($msb:expr, $lsb:expr)),+], $signed:expr $(, $access:ident)?) => {
+        impl $register {
+            #[allow(non_upper_case_globals)]
+            pub const $name: $crate::FieldInfo = $crate::FieldInfo {
+                msb: $crate::scattered::span(&[$(($msb, $lsb)),+]).0,
+                lsb: $crate::scattered::span(&[$(($msb, $lsb)),+]).1,
+                slices: &[$(($msb, $lsb)),+],
+                ..$crate::field_info!(@info $name, $signed $(, $access)?)
+            };
+        }
+
+        $crate::field_info!(@check $register, $name $(, $access)?);
+    };
+    ($register:ident, $name:ident, $msb:expr, $lsb:expr, $signed:expr $(, $access:ident)?) => {
+        impl $register {
+            #[allow(non_upper_case_globals)]
+            pub const $name: $crate::FieldInfo = $crate::FieldInfo {
+                msb: $msb,
+                lsb: $lsb,
+                ..$crate::field_info!(@info $name, $signed $(, $access)?)
+            };
+        }
+
+        $crate::field_info!(@check $register, $name $(, $access)?);
+    };
+    (@info $name:ident, $signed:expr $(, $access:ident)?) => {
+        $crate::FieldInfo {
+            name: stringify!($name),
+            msb: 0,
+            lsb: 0,
+            signed: $signed,
+            access: {
+                let access = $crate::Access::ReadWrite;
+                $(let access = $crate::Access::$access;)?
+                access
+            },
+            slices: &[],
+        }
+    };
+    (@check $register:ident, $name:ident $(, $access:ident)?) => {
+        const _: () = assert!(
+            $register::$name.msb < $register::WIDTH,
+            concat!(stringify!($register), "::", stringify!($name), " is outside of the register")
+        );
+
+        // The register masks only the fields in its access list
+        $(
+            const _: () = assert!(
+                $crate::FieldInfo::access_mask($register::ACCESS_FIELDS, $crate::Access::$access) & $register::$name.mask()
+                    == $register::$name.mask(),
+                concat!(stringify!($register), "::", stringify!($name), " has an access but isn't in the access list of its register")
+            );
+        )?
+    };
+}
+
+/// Sign extends a raw field `width` bits wide read as `val_type`: the field is shifted
+/// to the top of `val_type` and back, which fills the top bits of a signed type with
+/// the sign and leaves an unsigned one as it is. Used by the field getters.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! sign_extend {
+    ($value:expr, $val_type:ty, $width:expr) => {{
+        const SHIFT: u32 = <$val_type>::BITS - ($width);
+        ($value as $val_type) << SHIFT >> SHIFT
+    }};
+}
+
+/// This macro generates a bitfield within a register for a single bit. For example,
+/// if a register contains a bit flag, this could be used to get / set the bit
+/// flag in a named way.
+///
+/// Status and interrupt flags that the device clears or sets as a side effect are
+/// given their access, `W1C`, `W0C`, `RC` or `RS`, as in `bitfield!(DevInt1, ChgInt, 3, RC)`,
+/// and listed in the access list of their register, `register!(DevInt1, access: [ChgInt])`.
+/// The register collects them in its `W1C_MASK` and `W0C_MASK`, and bus_write and
+/// writes to memory mapped registers then write W1C flags as 0 and W0C flags as 1,
+/// so writing back the register doesn't clear them. `acknowledge` clears a single
+/// flag. A plain bit given `ReadWrite` can be acknowledged too.
+#[macro_export]
+macro_rules! bitfield {
+    ($register:ident, $bitfield_name:ident, $bit:literal $(, $access:ident)?) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitfield_name>]{
+                fn [<get_ $bitfield_name>](&self) -> bool;
+                fn [<set_ $bitfield_name>](&mut self, value: bool) -> &mut Self;
+            }
+
+            $crate::field_info!($register, $bitfield_name, $bit, $bit, false$(, $access)?);
+
+
+            impl [<$register _ $bitfield_name>] for $register {
+                fn [<get_ $bitfield_name>](&self) -> bool {
+                    self.register.read().is_set($bit)
+                }
+
+                fn [<set_ $bitfield_name>](&mut self, value: bool) -> &mut Self {
+                    if value {
+                        self.modify(|register| { register.set_bit($bit); });
+                    } else {
+                        self.modify(|register| { register.clear_bit($bit); });
+                    }
+
+                    self
+                }
+            }
+        }
+    };
+}
+
+/// Generates an enumerated type for a bitrange and should be done BEFORE the
+/// bitrange register is used to ensure the correct types are in place. This macro
+/// also takes care of converting the enum to a number and vice versa.
+#[macro_export]
+macro_rules! bitrange_enum_values {
+    ($enum_name:ident, $enum_type:ty, [$(($name:ident, $value:literal)),+]) => {
+        $crate::paste::paste! {
+            #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+            pub enum $enum_name {
+                $(
+                    $name = $value,
+                )+
+            }
+
+            pub fn [<$enum_name FromNum>](num: $enum_type) -> Option<$enum_name> {
+                match num {
+                    $(
+                        $value => Some($enum_name::$name),
+                    )+
+                    _ => None,
+                }
+            }
+
+            pub fn [<$enum_name ToNum>](enum_value: $enum_name) -> $enum_type {
+                enum_value as $enum_type
+            }
+        }
+    };
+}
+
+/// Defines a bitrange and the correct getters and setters for the bitrange using
+/// an enum defined using the bitrange_enum_values macro. This helps enforce
+/// only valid enum can be used for the getters and setters. If a register
+/// doesn't have an enum that is easily mappable, consider bitrange_raw instead.
+///
+/// As with bitfield!, a group of flags is given its access after the type, as in
+/// `bitrange!(Status, Faults, 6, 4, Faults, W1C)`.
+#[macro_export]
+macro_rules! bitrange {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty $(, $access:ident)?) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> Option<$val_type>;
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self;
+            }
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, false$(, $access)?);
+
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name>](&self) -> Option<$val_type> {
+                    let val = self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb });
+                    [<$val_type FromNum>](val)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self {
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, [<$val_type ToNum>](value)); });
+
+                    self
+                }
+            }
+        }
+    };
+}
+
+/// Defines a bitrange and the correct getters and setters for the bitrange using
+/// a raw type, such as u8, u16, u32, etc. This can be used for registers like
+/// ID and Revision that don't map to an enum but occupy a bitrange.
+///
+/// The setter returns Err(Errors::TooLow / TooHigh) if the value doesn't fit in the
+/// field, and the `_saturating` setter clamps the value to the field instead.
+///
+/// As with bitfield!, a group of flags is given its access after the type, as in
+/// `bitrange_raw!(Status, Pending, 6, 4, u8, W1C)`.
+#[macro_export]
+macro_rules! bitrange_raw {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty $(, $access:ident)?) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> $val_type;
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>>;
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: $val_type) -> $crate::Saturated<$val_type>;
+                fn [<get_ $bitrange_name _min>](&self) -> $val_type;
+                fn [<get_ $bitrange_name _max>](&self) -> $val_type;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb < <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, <$val_type>::MIN != 0$(, $access)?);
+
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name>](&self) -> $val_type {
+                    let value = $crate::sign_extend!(self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb }), $val_type, $msb - $lsb + 1);
+                    value
+                }
+
+                fn [<get_ $bitrange_name _min>](&self) -> $val_type {
+                    <$val_type>::MIN >> (<$val_type>::BITS - ($msb - $lsb + 1))
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> $val_type {
+                    <$val_type>::MAX >> (<$val_type>::BITS - ($msb - $lsb + 1))
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>> {
+                    let min = self.[<get_ $bitrange_name _min>]();
+                    let max = self.[<get_ $bitrange_name _max>]();
+                    if value < min {
+                        return Err($crate::Errors::TooLow { requested: value, min, max });
+                    }
+                    if value > max {
+                        return Err($crate::Errors::TooHigh { requested: value, min, max });
+                    }
+                    let comp = (value as $val_type);
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, comp as _); });
+
+                    Ok(self)
+                }
+
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: $val_type) -> $crate::Saturated<$val_type> {
+                    let clamped = value.clamp(self.[<get_ $bitrange_name _min>](), self.[<get_ $bitrange_name _max>]());
+                    let _ = self.[<set_ $bitrange_name>](clamped);
+                    $crate::Saturated { value: clamped, clamped: clamped != value }
+                }
+            }
+        }
+    };
+}
+
+/// Generates an array of single bit flags within a register, such as 8 channel
+/// enables. Element `i` is bit `lsb + i * stride`. The getter and setter take the
+/// element and panic if it is out of range, like indexing a slice, and `_iter`
+/// returns the value of every element.
+///
+/// `Register::name[i]` is the FieldInfo of element `i`. The elements share a name,
+/// so an array can't be listed in the `fields` section of peripheral!.
+///
+/// ```ignore
+/// bitfield_array!(IntMask, ch_en, 0, 8, 1); // bits 0 to 7
+/// int_mask.set_ch_en(3, true);
+/// ```
+#[macro_export]
+macro_rules! bitfield_array {
+    ($register:ident, $bitfield_name:ident, $lsb:literal, $count:literal, $stride:literal) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitfield_name>] {
+                const [<$bitfield_name:upper _COUNT>]: usize = $count;
+                fn [<get_ $bitfield_name>](&self, element: usize) -> bool;
+                fn [<set_ $bitfield_name>](&mut self, element: usize, value: bool) -> &mut Self;
+                fn [<$bitfield_name _iter>](&self) -> impl Iterator<Item = bool> + '_;
+            }
+
+            const _: () = assert!(
+                $lsb + ($count - 1) * $stride < $register::WIDTH,
+                concat!(stringify!($register), "::", stringify!($bitfield_name), " doesn't fit in the register")
+            );
+
+            impl $register {
+                #[allow(non_upper_case_globals)]
+                pub const $bitfield_name: [$crate::FieldInfo; $count] = {
+                    let mut fields = [$crate::FieldInfo {
+                        name: stringify!($bitfield_name),
+                        msb: $lsb,
+                        lsb: $lsb,
+                        signed: false,
+                        access: $crate::Access::ReadWrite,
+                        slices: &[],
+                    }; $count];
+                    let mut i = 0;
+                    while i < $count {
+                        fields[i].lsb = $lsb + i as u32 * $stride;
+                        fields[i].msb = fields[i].lsb;
+                        i += 1;
+                    }
+                    fields
+                };
+            }
+
+            impl [<$register _ $bitfield_name>] for $register {
+                fn [<get_ $bitfield_name>](&self, element: usize) -> bool {
+                    assert!(element < $count, concat!(stringify!($bitfield_name), " element out of range"));
+                    self.register.read().is_set(($lsb + element * $stride) as u32)
+                }
+
+                fn [<set_ $bitfield_name>](&mut self, element: usize, value: bool) -> &mut Self {
+                    assert!(element < $count, concat!(stringify!($bitfield_name), " element out of range"));
+                    let bit = ($lsb + element * $stride) as u32;
+                    if value {
+                        self.modify(|register| { register.set_bit(bit); });
+                    } else {
+                        self.modify(|register| { register.clear_bit(bit); });
+                    }
+
+                    self
+                }
+
+                fn [<$bitfield_name _iter>](&self) -> impl Iterator<Item = bool> + '_ {
+                    (0..$count).map(move |i| self.[<get_ $bitfield_name>](i))
+                }
+            }
+        }
+    };
+}
+
+/// Generates an array of identical multi-bit groups within a register, such as the
+/// 2 bit mode of each pin of a GPIO register. Element `i` occupies `width` bits
+/// starting at bit `lsb + i * stride`, and is read as `val_type` (sign extended if
+/// signed). The getter and setter take the element and panic if it is out of range,
+/// like indexing a slice, and the setter checks the value as bitrange_raw! does.
+/// `Register::name[i]` is the FieldInfo of element `i`, as with bitfield_array!.
+///
+/// ```ignore
+/// bitrange_array!(GpioMode, mode, 0, 2, 8, 2, u8); // 8 pins, bits 1:0 to 15:14
+/// gpio_mode.set_mode(5, 0b10)?;
+/// ```
+#[macro_export]
+macro_rules! bitrange_array {
+    ($register:ident, $bitrange_name:ident, $lsb:literal, $width:literal, $count:literal, $stride:literal, $val_type:ty) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                const [<$bitrange_name:upper _COUNT>]: usize = $count;
+                fn [<get_ $bitrange_name>](&self, element: usize) -> $val_type;
+                fn [<set_ $bitrange_name>](&mut self, element: usize, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>>;
+                fn [<get_ $bitrange_name _min>](&self) -> $val_type;
+                fn [<get_ $bitrange_name _max>](&self) -> $val_type;
+                fn [<$bitrange_name _iter>](&self) -> impl Iterator<Item = $val_type> + '_;
+            }
+
+            const _: () = assert!(
+                $width <= <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+            const _: () = assert!(
+                $width <= $stride && $lsb + ($count - 1) * $stride + $width <= $register::WIDTH,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " doesn't fit in the register")
+            );
+
+            impl $register {
+                #[allow(non_upper_case_globals)]
+                pub const $bitrange_name: [$crate::FieldInfo; $count] = {
+                    let mut fields = [$crate::FieldInfo {
+                        name: stringify!($bitrange_name),
+                        msb: $lsb + $width - 1,
+                        lsb: $lsb,
+                        signed: <$val_type>::MIN != 0,
+                        access: $crate::Access::ReadWrite,
+                        slices: &[],
+                    }; $count];
+                    let mut i = 0;
+                    while i < $count {
+                        fields[i].lsb = $lsb + i as u32 * $stride;
+                        fields[i].msb = fields[i].lsb + $width - 1;
+                        i += 1;
+                    }
+                    fields
+                };
+            }
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name>](&self, element: usize) -> $val_type {
+                    assert!(element < $count, concat!(stringify!($bitrange_name), " element out of range"));
+                    let lsb = ($lsb + element * $stride) as u32;
+                    let range = $crate::BitRange { stop_bit: lsb + $width - 1, start_bit: lsb };
+                    let value = $crate::sign_extend!(self.register.read().get_range(range), $val_type, $width);
+                    value
+                }
+
+                fn [<get_ $bitrange_name _min>](&self) -> $val_type {
+                    <$val_type>::MIN >> (<$val_type>::BITS - $width)
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> $val_type {
+                    <$val_type>::MAX >> (<$val_type>::BITS - $width)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, element: usize, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>> {
+                    assert!(element < $count, concat!(stringify!($bitrange_name), " element out of range"));
+                    let min = self.[<get_ $bitrange_name _min>]();
+                    let max = self.[<get_ $bitrange_name _max>]();
+                    if value < min {
+                        return Err($crate::Errors::TooLow { requested: value, min, max });
+                    }
+                    if value > max {
+                        return Err($crate::Errors::TooHigh { requested: value, min, max });
+                    }
+                    let lsb = ($lsb + element * $stride) as u32;
+                    let range = $crate::BitRange { stop_bit: lsb + $width - 1, start_bit: lsb };
+                    self.modify(|register| { register.set_range(range, value as _); });
+
+                    Ok(self)
+                }
+
+                fn [<$bitrange_name _iter>](&self) -> impl Iterator<Item = $val_type> + '_ {
+                    (0..$count).map(move |i| self.[<get_ $bitrange_name>](i))
+                }
+            }
+        }
+    };
+}
+
+/// Defines a bitrange and the correct getters and setters for the bitrange using
+/// a quantization value and a data type which quantizes the bitfield to an f32.
+///
+/// For example, on the Max17261, some 16 bit registers are two 8 bit registers that
+/// represent a real value such as voltage, temperate, etc. This macro allows
+/// named and range checked access to the register in human readable units.
+/// The macro requires a quantization value and the floating point min and max expected.
+/// This is used to test input values when the setter is called; if the setter value
+/// is outside the min / max range, the setter returns Err(Errors::TooLow / TooHigh),
+/// otherwise the value is rounded to a quantization step and Ok(value) is returned
+/// with the physical value that was actually stored.
+///
+/// The min and max can be left out, in which case they are computed from the width
+/// and sign of the field, the quantization and the offset. A narrower range can be
+/// given with `min = value, max = value` after the quantization (and offset), and
+/// is checked at compile time to be within what the field can represent:
+///
+/// ```ignore
+/// bitrange_quantized!(TAlrtTh, tmax, 15, 8, i8, 1.0); // -128.0..=127.0
+/// bitrange_quantized!(VEmpty, ve, 15, 7, u16, 0.01, min = 2.5, max = 5.0);
+/// ```
+///
+/// An optional `offset = value` can be added for fields with a zero point, such as
+/// a temperature of raw * 0.5 - 40. The value is then raw * quantization + offset
+/// in both the getter and setter, and the min / max are given including the offset.
+/// `get_<name>_min` and `get_<name>_max` return them narrowed to the raw range of the
+/// field plus the offset.
+///
+/// An optional `rounding = Nearest | Floor | Ceil | Exact` selects how values between
+/// two steps are stored, see `Rounding`. The default is `Nearest`, and `Exact` makes
+/// the setter return Err(Errors::QuantizationError) for values that aren't on a step.
+#[macro_export]
+macro_rules! bitrange_quantized {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $quantization:expr $(, offset = $offset:expr)?, min = $min:expr, max = $max:expr $(, rounding = $rounding:ident)?) => {
+        const _: () = {
+            let (field_min, field_max) = $crate::quantize::field_limits(
+                (<$val_type>::MIN >> (<$val_type>::BITS - ($msb - $lsb + 1))) as f32,
+                (<$val_type>::MAX >> (<$val_type>::BITS - ($msb - $lsb + 1))) as f32,
+                $quantization as f32,
+                0.0 $(+ ($offset as f32))?,
+            );
+            // Allow half a step for floating point error in the limits
+            let tolerance = $quantization as f32 / 2.0;
+            assert!(
+                $min as f32 <= $max as f32 && $min as f32 >= field_min - tolerance && $max as f32 <= field_max + tolerance,
+                concat!("range of ", stringify!($register), "::", stringify!($bitrange_name), " doesn't fit in the field")
+            );
+        };
+        $crate::bitrange_quantized!($register, $bitrange_name, $msb, $lsb, $val_type, $quantization, $min, $max $(, offset = $offset)? $(, rounding = $rounding)?);
+    };
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $quantization:expr $(, offset = $offset:expr)? $(, rounding = $rounding:ident)?) => {
+        $crate::bitrange_quantized!(
+            $register, $bitrange_name, $msb, $lsb, $val_type, $quantization,
+            $crate::quantize::field_limits(
+                (<$val_type>::MIN >> (<$val_type>::BITS - ($msb - $lsb + 1))) as f32,
+                (<$val_type>::MAX >> (<$val_type>::BITS - ($msb - $lsb + 1))) as f32,
+                $quantization as f32,
+                0.0 $(+ ($offset as f32))?,
+            ).0,
+            $crate::quantize::field_limits(
+                (<$val_type>::MIN >> (<$val_type>::BITS - ($msb - $lsb + 1))) as f32,
+                (<$val_type>::MAX >> (<$val_type>::BITS - ($msb - $lsb + 1))) as f32,
+                $quantization as f32,
+                0.0 $(+ ($offset as f32))?,
+            ).1
+            $(, offset = $offset)? $(, rounding = $rounding)?
+        );
+    };
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $quantization:expr, $min:expr, $max:expr $(, offset = $offset:expr)? $(, rounding = $rounding:ident)?) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> f32;
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors>;
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: f32) -> Result<$crate::Saturated<f32>, $crate::Errors>;
+                fn [<get_ $bitrange_name _quatization>](&self) -> f32;
+                fn [<get_ $bitrange_name _offset>](&self) -> f32;
+                fn [<get_ $bitrange_name _rounding>](&self) -> $crate::Rounding;
+                fn [<get_ $bitrange_name _min>](&self) -> f32;
+                fn [<get_ $bitrange_name _max>](&self) -> f32;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb < <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, <$val_type>::MIN != 0);
+
+            impl $register {
+                fn [<$bitrange_name _quantization>]() -> $crate::quantize::Quantization {
+                    const SHIFT: u32 = <$val_type>::BITS - ($msb - $lsb + 1);
+                    let rounding = $crate::Rounding::Nearest;
+                    $(let rounding = $crate::Rounding::$rounding;)?
+                    $crate::quantize::Quantization {
+                        step: $quantization as f32,
+                        offset: 0.0 $(+ ($offset as f32))?,
+                        min: $min as f32,
+                        max: $max as f32,
+                        field_min: (<$val_type>::MIN >> SHIFT) as f32,
+                        field_max: (<$val_type>::MAX >> SHIFT) as f32,
+                        rounding,
+                    }
+                }
+            }
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name _quatization>](&self) -> f32 {
+                    $quantization
+                }
+
+                fn [<get_ $bitrange_name _offset>](&self) -> f32 {
+                    Self::[<$bitrange_name _quantization>]().offset
+                }
+
+                fn [<get_ $bitrange_name _rounding>](&self) -> $crate::Rounding {
+                    Self::[<$bitrange_name _quantization>]().rounding
+                }
+
+                fn [<get_ $bitrange_name _min>](&self) -> f32 {
+                    Self::[<$bitrange_name _quantization>]().limits().0
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> f32 {
+                    Self::[<$bitrange_name _quantization>]().limits().1
+                }
+
+                fn [<get_ $bitrange_name>](&self) -> f32 {
+                    let value = $crate::sign_extend!(self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb }), $val_type, $msb - $lsb + 1);
+                    (value as f32) * $quantization as f32 + self.[<get_ $bitrange_name _offset>]()
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors> {
+                    let quant_val = Self::[<$bitrange_name _quantization>]().to_steps(value)? as $val_type;
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, quant_val as _); });
+                    Ok(self.[<get_ $bitrange_name>]())
+                }
+
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: f32) -> Result<$crate::Saturated<f32>, $crate::Errors> {
+                    match self.[<set_ $bitrange_name>](value) {
+                        Ok(value) => Ok($crate::Saturated { value, clamped: false }),
+                        Err($crate::Errors::TooLow { min, .. }) => self
+                            .[<set_ $bitrange_name>](min)
+                            .map(|value| $crate::Saturated { value, clamped: true }),
+                        Err($crate::Errors::TooHigh { max, .. }) => self
+                            .[<set_ $bitrange_name>](max)
+                            .map(|value| $crate::Saturated { value, clamped: true }),
+                        Err(e) => Err(e),
+                    }
+                }
+            }
+        }
+    };
+}
+
+/// Same as bitrange_quantized!, but the getters and setters use a typed quantity
+/// from the `uom` crate instead of a bare f32, so that volts can't be mixed up with
+/// millivolts. Requires the `uom` feature.
+///
+/// The quantity is one of the `uom::si::f32` types, such as `ElectricPotential`,
+/// `ElectricCurrent`, `ThermodynamicTemperature`, `Time` or `ElectricCharge`, and the
+/// unit is one of the units of that quantity, such as `millivolt`. The quantization,
+/// min, max and offset are given in that unit.
+///
+/// ```ignore
+/// register!(VCell);
+/// bitrange_quantity!(VCell, voltage, 15, 0, u16, ElectricPotential, microvolt, 78.125, 0.0, 5_119_921.875);
+/// let volts = max17261.VCell().get_voltage().get::<uom::si::electric_potential::volt>();
+/// ```
+#[cfg(feature = "uom")]
+#[macro_export]
+macro_rules! bitrange_quantity {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $quantity:ident, $unit:ident, $quantization:expr, $min:expr, $max:expr $(, offset = $offset:expr)? $(, rounding = $rounding:ident)?) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> $crate::uom::si::f32::$quantity;
+                fn [<set_ $bitrange_name>](&mut self, value: $crate::uom::si::f32::$quantity) -> Result<$crate::uom::si::f32::$quantity, $crate::Errors<$crate::uom::si::f32::$quantity>>;
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: $crate::uom::si::f32::$quantity) -> Result<$crate::Saturated<$crate::uom::si::f32::$quantity>, $crate::Errors<$crate::uom::si::f32::$quantity>>;
+                fn [<get_ $bitrange_name _min>](&self) -> $crate::uom::si::f32::$quantity;
+                fn [<get_ $bitrange_name _max>](&self) -> $crate::uom::si::f32::$quantity;
+                fn [<get_ $bitrange_name _rounding>](&self) -> $crate::Rounding;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb < <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, <$val_type>::MIN != 0);
+
+            impl $register {
+                fn [<$bitrange_name _quantization>]() -> $crate::quantize::Quantization {
+                    const SHIFT: u32 = <$val_type>::BITS - ($msb - $lsb + 1);
+                    let rounding = $crate::Rounding::Nearest;
+                    $(let rounding = $crate::Rounding::$rounding;)?
+                    $crate::quantize::Quantization {
+                        step: $quantization as f32,
+                        offset: 0.0 $(+ ($offset as f32))?,
+                        min: $min as f32,
+                        max: $max as f32,
+                        field_min: (<$val_type>::MIN >> SHIFT) as f32,
+                        field_max: (<$val_type>::MAX >> SHIFT) as f32,
+                        rounding,
+                    }
+                }
+
+                fn [<$bitrange_name _store>](&mut self, value: f32) -> Result<$crate::uom::si::f32::$quantity, $crate::Errors<$crate::uom::si::f32::$quantity>> {
+                    let quant_val = Self::[<$bitrange_name _quantization>]()
+                        .to_steps(value)
+                        .map_err(|e| e.map(Self::[<$bitrange_name _from_f32>]))? as $val_type;
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, quant_val as _); });
+                    Ok(<Self as [<$register _ $bitrange_name>]>::[<get_ $bitrange_name>](self))
+                }
+
+                fn [<$bitrange_name _from_f32>](value: f32) -> $crate::uom::si::f32::$quantity {
+                    $crate::uom::si::f32::$quantity::new::<$crate::uom::si::[<$quantity:snake>]::$unit>(value)
+                }
+            }
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name _min>](&self) -> $crate::uom::si::f32::$quantity {
+                    Self::[<$bitrange_name _from_f32>](Self::[<$bitrange_name _quantization>]().limits().0)
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> $crate::uom::si::f32::$quantity {
+                    Self::[<$bitrange_name _from_f32>](Self::[<$bitrange_name _quantization>]().limits().1)
+                }
+
+                fn [<get_ $bitrange_name _rounding>](&self) -> $crate::Rounding {
+                    Self::[<$bitrange_name _quantization>]().rounding
+                }
+
+                fn [<get_ $bitrange_name>](&self) -> $crate::uom::si::f32::$quantity {
+                    let value = $crate::sign_extend!(self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb }), $val_type, $msb - $lsb + 1);
+                    Self::[<$bitrange_name _from_f32>](Self::[<$bitrange_name _quantization>]().to_value(value as f32))
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: $crate::uom::si::f32::$quantity) -> Result<$crate::uom::si::f32::$quantity, $crate::Errors<$crate::uom::si::f32::$quantity>> {
+                    self.[<$bitrange_name _store>](value.get::<$crate::uom::si::[<$quantity:snake>]::$unit>())
+                }
+
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: $crate::uom::si::f32::$quantity) -> Result<$crate::Saturated<$crate::uom::si::f32::$quantity>, $crate::Errors<$crate::uom::si::f32::$quantity>> {
+                    // Clamp in the declared unit, converting the limits back and forth
+                    // between units could land just outside of the range
+                    let requested = value.get::<$crate::uom::si::[<$quantity:snake>]::$unit>();
+                    let (min, max) = Self::[<$bitrange_name _quantization>]().limits();
+                    let clamped = requested.clamp(min, max);
+                    let value = self.[<$bitrange_name _store>](clamped)?;
+                    Ok($crate::Saturated { value, clamped: clamped != requested })
+                }
+            }
+        }
+    };
+}
+
+/// Defines a bitrange whose value is looked up in a table of `(raw, value)`
+/// entries, for fields where each code maps to an arbitrary value, such as the
+/// times of the Max17261 HibCfg register. The table is a const array or slice of
+/// `($val_type, f32)`, and every code must fit in the field or the macro fails to
+/// compile.
+///
+/// The getter returns None if the raw value isn't in the table. The setter stores
+/// the code of the entry nearest to the value and returns Ok(value) with the value
+/// of that entry, or Err(Errors::TooLow / TooHigh) if the value is outside of the
+/// smallest and largest value in the table.
+#[macro_export]
+macro_rules! bitrange_table {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $table:expr) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> Option<f32>;
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors>;
+                fn [<get_ $bitrange_name _table>](&self) -> &'static [($val_type, f32)];
+                fn [<get_ $bitrange_name _min>](&self) -> f32;
+                fn [<get_ $bitrange_name _max>](&self) -> f32;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb < <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, <$val_type>::MIN != 0);
+
+            const _: () = {
+                const SHIFT: u32 = <$val_type>::BITS - ($msb - $lsb + 1);
+                let entries: &[($val_type, f32)] = &$table;
+                let mut i = 0;
+                while i < entries.len() {
+                    assert!(
+                        entries[i].0 >= <$val_type>::MIN >> SHIFT && entries[i].0 <= <$val_type>::MAX >> SHIFT,
+                        concat!("a code of ", stringify!($register), "::", stringify!($bitrange_name), " doesn't fit in the field")
+                    );
+                    i += 1;
+                }
+            };
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name _table>](&self) -> &'static [($val_type, f32)] {
+                    &$table
+                }
+
+                fn [<get_ $bitrange_name _min>](&self) -> f32 {
+                    $crate::lookup::limits(self.[<get_ $bitrange_name _table>]()).0
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> f32 {
+                    $crate::lookup::limits(self.[<get_ $bitrange_name _table>]()).1
+                }
+
+                fn [<get_ $bitrange_name>](&self) -> Option<f32> {
+                    let value = $crate::sign_extend!(self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb }), $val_type, $msb - $lsb + 1);
+                    $crate::lookup::table_value(self.[<get_ $bitrange_name _table>](), value)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors> {
+                    let table = self.[<get_ $bitrange_name _table>]();
+                    // Every code was checked to fit in the field at compile time
+                    let raw = $crate::lookup::table_raw(table, value)?;
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, raw as _); });
+                    Ok($crate::lookup::table_value(table, raw).unwrap_or(value))
+                }
+            }
+        }
+    };
+}
+
+/// Defines a bitrange whose value is linearly interpolated between `(raw, value)`
+/// breakpoints, for fields that follow a curve such as a thermistor. The breakpoints
+/// are a const array or slice of `($val_type, f32)` sorted by raw value, and the
+/// values may increase or decrease along the curve. Every raw value must fit in the
+/// field or the macro fails to compile.
+///
+/// The getter returns None if the raw value is outside of the breakpoints. The
+/// setter stores the nearest raw value on the curve and returns Ok(value) with the
+/// value that was actually stored, or Err(Errors::TooLow / TooHigh) if the value
+/// is outside of the smallest and largest value of the breakpoints.
+#[macro_export]
+macro_rules! bitrange_piecewise {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $points:expr) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> Option<f32>;
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors>;
+                fn [<get_ $bitrange_name _breakpoints>](&self) -> &'static [($val_type, f32)];
+                fn [<get_ $bitrange_name _min>](&self) -> f32;
+                fn [<get_ $bitrange_name _max>](&self) -> f32;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb < <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, <$val_type>::MIN != 0);
+
+            const _: () = {
+                const SHIFT: u32 = <$val_type>::BITS - ($msb - $lsb + 1);
+                let entries: &[($val_type, f32)] = &$points;
+                let mut i = 0;
+                while i < entries.len() {
+                    assert!(
+                        entries[i].0 >= <$val_type>::MIN >> SHIFT && entries[i].0 <= <$val_type>::MAX >> SHIFT,
+                        concat!("a breakpoint of ", stringify!($register), "::", stringify!($bitrange_name), " doesn't fit in the field")
+                    );
+                    i += 1;
+                }
+            };
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name _breakpoints>](&self) -> &'static [($val_type, f32)] {
+                    &$points
+                }
+
+                fn [<get_ $bitrange_name _min>](&self) -> f32 {
+                    $crate::lookup::limits(self.[<get_ $bitrange_name _breakpoints>]()).0
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> f32 {
+                    $crate::lookup::limits(self.[<get_ $bitrange_name _breakpoints>]()).1
+                }
+
+                fn [<get_ $bitrange_name>](&self) -> Option<f32> {
+                    let value = $crate::sign_extend!(self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb }), $val_type, $msb - $lsb + 1);
+                    $crate::lookup::piecewise_value(self.[<get_ $bitrange_name _breakpoints>](), value)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors> {
+                    let points = self.[<get_ $bitrange_name _breakpoints>]();
+                    // Interpolating between breakpoints that fit in the field, checked at
+                    // compile time, always gives a raw value that fits
+                    let raw = $crate::lookup::piecewise_raw(points, value)? as $val_type;
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, raw as _); });
+                    Ok($crate::lookup::piecewise_value(points, raw).unwrap_or(value))
+                }
+            }
+        }
+    };
+}
+
+/// Defines a 16 bit PMBus LINEAR11 field, a 5 bit exponent and 11 bit mantissa
+/// used by most PMBus telemetry such as READ_IOUT and READ_TEMPERATURE_1.
+///
+/// The getter returns the value as an f32. The setter encodes the value with the
+/// smallest exponent that fits, keeping as much precision as possible, and returns
+/// Ok(value) with the value actually stored, or Err(Errors::TooLow / TooHigh) if the
+/// value can't be represented.
+#[macro_export]
+macro_rules! bitrange_linear11 {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> f32;
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors>;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb == 15,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " must be 16 bits wide")
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, false);
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name>](&self) -> f32 {
+                    let raw = self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb });
+                    $crate::pmbus::linear11_to_f32(raw as u16)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors> {
+                    let raw = $crate::pmbus::f32_to_linear11(value)?;
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, raw as _); });
+                    Ok($crate::pmbus::linear11_to_f32(raw))
+                }
+            }
+        }
+    };
+}
+
+/// Defines a 16 bit PMBus LINEAR16 field, an unsigned mantissa used by the output
+/// voltage commands such as READ_VOUT and VOUT_COMMAND. The exponent comes from the
+/// VOUT_MODE command, so the getter and setter take it as an argument; use
+/// `bitterly::pmbus::vout_mode_exponent` to get it from the VOUT_MODE byte.
+///
+/// The setter rounds to the nearest mantissa and returns Ok(value) with the value
+/// actually stored, or Err(Errors::TooLow / TooHigh) if the value is negative or too
+/// large for the exponent.
+#[macro_export]
+macro_rules! bitrange_linear16 {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self, exponent: i8) -> f32;
+                fn [<set_ $bitrange_name>](&mut self, value: f32, exponent: i8) -> Result<f32, $crate::Errors>;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb == 15,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " must be 16 bits wide")
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, false);
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name>](&self, exponent: i8) -> f32 {
+                    let raw = self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb });
+                    $crate::pmbus::linear16_to_f32(raw as u16, exponent)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: f32, exponent: i8) -> Result<f32, $crate::Errors> {
+                    let raw = $crate::pmbus::f32_to_linear16(value, exponent)?;
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, raw as _); });
+                    Ok($crate::pmbus::linear16_to_f32(raw, exponent))
+                }
+            }
+        }
+    };
+}
+
+/// Defines a 16 bit PMBus DIRECT field with the `m`, `b` and `R` coefficients from
+/// the device datasheet (or COEFFICIENTS command), value = (Y * 10^-R - b) / m.
+///
+/// The setter rounds to the nearest raw value and returns Ok(value) with the value
+/// actually stored, or Err(Errors::TooLow / TooHigh) if the raw value doesn't fit
+/// in 16 bits.
+#[macro_export]
+macro_rules! bitrange_direct {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $m:expr, $b:expr, $r:expr) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> f32;
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors>;
+                fn [<get_ $bitrange_name _coefficients>](&self) -> (i16, i16, i8);
+            }
+
+            const _: () = assert!(
+                $msb - $lsb == 15,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " must be 16 bits wide")
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, false);
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name _coefficients>](&self) -> (i16, i16, i8) {
+                    ($m, $b, $r)
+                }
+
+                fn [<get_ $bitrange_name>](&self) -> f32 {
+                    let (m, b, r) = self.[<get_ $bitrange_name _coefficients>]();
+                    let raw = self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb });
+                    $crate::pmbus::direct_to_f32(raw as i16, m, b, r)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors> {
+                    let (m, b, r) = self.[<get_ $bitrange_name _coefficients>]();
+                    let raw = $crate::pmbus::f32_to_direct(value, m, b, r)?;
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, raw as u16 as _); });
+                    Ok($crate::pmbus::direct_to_f32(raw, m, b, r))
+                }
+            }
+        }
+    };
+}
+
+/// Defines a field that is split across several registers, such as a 32 bit timer
+/// stored in two 16 bit registers. The parts are listed from the most significant
+/// to the least significant as `(Register, msb, lsb)`, and are concatenated into
+/// `$val_type`, which is sign extended for signed types.
+///
+/// Because the field belongs to more than one register, the getter and setter are
+/// generated on the peripheral, and its variants, rather than on a register. The setter returns
+/// Err(Errors::TooLow / TooHigh) if the value doesn't fit in the combined width, and
+/// otherwise writes every part. The registers are named as in the peripheral! map, and
+/// the peripheral of a named backer is given first, as `bitrange_multi_raw!(Adc: adc, ...)`.
+///
+/// ```ignore
+/// bitrange_multi_raw!(adc, i32, [(AdcHigh, 7, 0), (AdcMid, 7, 0), (AdcLow, 7, 4)]); // 20 bits
+/// let value = adc.get_adc();
+/// ```
+#[macro_export]
+macro_rules! bitrange_multi_raw {
+    ($peripheral:ident: $name:ident, $val_type:ty, [$(($register:ident, $msb:literal, $lsb:literal)),+]) => {
+        $crate::paste::paste! {
+            const _: () = assert!(
+                0 $(+ ($msb - $lsb + 1))+ <= <$val_type>::BITS,
+                concat!(stringify!($name), " is wider than ", stringify!($val_type))
+            );
+            $(
+                const _: () = assert!(
+                    $msb < $peripheral::[<$register _WIDTH>],
+                    concat!(stringify!($name), " is outside of ", stringify!($register))
+                );
+            )+
+
+            pub trait [<$peripheral _ $name>] {
+                fn [<get_ $name _min>](&self) -> $val_type;
+                fn [<get_ $name _max>](&self) -> $val_type;
+                fn [<get_ $name>](&self) -> $val_type;
+                fn [<set_ $name>](&mut self, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>>;
+            }
+
+            impl<P: $crate::Family<Base = $peripheral>> [<$peripheral _ $name>] for P {
+                fn [<get_ $name _min>](&self) -> $val_type {
+                    <$val_type>::MIN >> (<$val_type>::BITS - (0 $(+ ($msb - $lsb + 1))+))
+                }
+
+                fn [<get_ $name _max>](&self) -> $val_type {
+                    <$val_type>::MAX >> (<$val_type>::BITS - (0 $(+ ($msb - $lsb + 1))+))
+                }
+
+                fn [<get_ $name>](&self) -> $val_type {
+                    const {
+                        $(
+                            assert!(
+                                P::PRESENT[$peripheral::[<$register _INDEX>]],
+                                concat!(stringify!($register), " isn't a register of this variant")
+                            );
+                        )+
+                    };
+                    let mut value: u128 = 0;
+                    $(
+                        value = (value << ($msb - $lsb + 1))
+                            | self.backer($peripheral::[<$register _INDEX>]).read().get_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }) as u128;
+                    )+
+                    $crate::sign_extend!(value, $val_type, (0 $(+ ($msb - $lsb + 1))+))
+                }
+
+                fn [<set_ $name>](&mut self, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>> {
+                    let min = self.[<get_ $name _min>]();
+                    let max = self.[<get_ $name _max>]();
+                    if value < min {
+                        return Err($crate::Errors::TooLow { requested: value, min, max });
+                    }
+                    if value > max {
+                        return Err($crate::Errors::TooHigh { requested: value, min, max });
+                    }
+
+                    let value = value as u128;
+                    let mut remaining: u32 = 0 $(+ ($msb - $lsb + 1))+;
+                    $(
+                        remaining -= $msb - $lsb + 1;
+                        let backer = self.backer($peripheral::[<$register _INDEX>]);
+                        backer.modify(|register| {
+                            register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, (value >> remaining) as _);
+                            // As in the register structs, leave W1C and W0C fields of memory mapped registers unchanged
+                            if backer.is_memory_mapped() {
+                                let (w1c, w0c) = $peripheral::WRITE_MASKS[$peripheral::[<$register _INDEX>]];
+                                register.update((register.contents() as u128 & !w1c | w0c) as _);
+                            }
+                        });
+                    )+
+                    Ok(self)
+                }
+            }
+        }
+    };
+    ($name:ident, $val_type:ty, [$(($register:ident, $msb:literal, $lsb:literal)),+]) => {
+        $crate::bitrange_multi_raw!(PeripheralType: $name, $val_type, [$(($register, $msb, $lsb)),+]);
+    };
+}
+
+/// Same as bitrange_multi_raw!, but the combined raw value is quantized to an f32
+/// as with bitrange_quantized!. The min and max are derived from the combined width,
+/// and the optional `offset = value` and `rounding = mode` are supported. The raw
+/// value is available through `get_<name>_raw` and `set_<name>_raw`.
+///
+/// The quantization is computed in f64, so every raw value of a field up to 53 bits
+/// wide is a whole step. The getter and setter still take an f32, whose resolution
+/// is coarser than one step once the raw value is above 2^24, so use the raw accessors
+/// where every step matters. The field isn't a FieldInfo of any one register, and
+/// isn't listed by `Peripheral::fields`.
+///
+/// ```ignore
+/// bitrange_multi_quantized!(timer, u32, [(TimerHSB, 15, 0), (TimerLSB, 15, 0)], 0.1758);
+/// let seconds = max17261.get_timer();
+/// ```
+#[macro_export]
+macro_rules! bitrange_multi_quantized {
+    ($peripheral:ident: $name:ident, $val_type:ty, [$(($register:ident, $msb:literal, $lsb:literal)),+], $quantization:expr $(, offset = $offset:expr)? $(, rounding = $rounding:ident)?) => {
+        $crate::paste::paste! {
+            $crate::bitrange_multi_raw!($peripheral: [<$name _raw>], $val_type, [$(($register, $msb, $lsb)),+]);
+
+            pub trait [<$peripheral _ $name>] {
+                fn [<$name _quantization>]() -> $crate::quantize::Quantization<f64>;
+                fn [<get_ $name _min>](&self) -> f32;
+                fn [<get_ $name _max>](&self) -> f32;
+                fn [<get_ $name>](&self) -> f32;
+                fn [<set_ $name>](&mut self, value: f32) -> Result<f32, $crate::Errors>;
+            }
+
+            impl<P: $crate::Family<Base = $peripheral>> [<$peripheral _ $name>] for P {
+                fn [<$name _quantization>]() -> $crate::quantize::Quantization<f64> {
+                    const SHIFT: u32 = <$val_type>::BITS - (0 $(+ ($msb - $lsb + 1))+);
+                    let field_min = (<$val_type>::MIN >> SHIFT) as f64;
+                    let field_max = (<$val_type>::MAX >> SHIFT) as f64;
+                    let step = $quantization as f64;
+                    let offset = 0.0 $(+ ($offset as f64))?;
+                    let a = field_min * step + offset;
+                    let b = field_max * step + offset;
+                    let rounding = $crate::Rounding::Nearest;
+                    $(let rounding = $crate::Rounding::$rounding;)?
+                    $crate::quantize::Quantization {
+                        step,
+                        offset,
+                        min: a.min(b),
+                        max: a.max(b),
+                        field_min,
+                        field_max,
+                        rounding,
+                    }
+                }
+
+                fn [<get_ $name _min>](&self) -> f32 {
+                    Self::[<$name _quantization>]().limits().0 as f32
+                }
+
+                fn [<get_ $name _max>](&self) -> f32 {
+                    Self::[<$name _quantization>]().limits().1 as f32
+                }
+
+                fn [<get_ $name>](&self) -> f32 {
+                    Self::[<$name _quantization>]().to_value(self.[<get_ $name _raw>]() as f64) as f32
+                }
+
+                fn [<set_ $name>](&mut self, value: f32) -> Result<f32, $crate::Errors> {
+                    let steps = Self::[<$name _quantization>]()
+                        .to_steps(value as f64)
+                        .map_err(|e| e.map(|value| value as f32))?;
+                    // to_steps only returns values that fit in the field
+                    let _ = self.[<set_ $name _raw>](steps as $val_type);
+                    Ok(self.[<get_ $name>]())
+                }
+            }
+        }
+    };
+    ($name:ident, $val_type:ty, [$(($register:ident, $msb:literal, $lsb:literal)),+], $quantization:expr $(, offset = $offset:expr)? $(, rounding = $rounding:ident)?) => {
+        $crate::bitrange_multi_quantized!(PeripheralType: $name, $val_type, [$(($register, $msb, $lsb)),+], $quantization $(, offset = $offset)? $(, rounding = $rounding)?);
+    };
+}
+
+/// Defines a field stored in several non-contiguous bit slices of one register, such
+/// as an 8 bit threshold in bits 15..12 and 3..0. The slices are listed from the most
+/// significant part of the value to the least significant as `(msb, lsb)`, and are
+/// concatenated into `$val_type`, which is sign extended for signed types. The getter
+/// and setter otherwise behave as bitrange_raw!.
+///
+/// ```ignore
+/// bitrange_scattered_raw!(Config, threshold, [(15, 12), (3, 0)], u8);
+/// ```
+#[macro_export]
+macro_rules! bitrange_scattered_raw {
+    ($register:ident, $bitrange_name:ident, [$(($msb:literal, $lsb:literal)),+], $val_type:ty) => {
+        $crate::paste::paste! {
             pub trait [<$register _ $bitrange_name>] {
                 fn [<get_ $bitrange_name>](&self) -> $val_type;
-                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self;
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>>;
+                fn [<get_ $bitrange_name _min>](&self) -> $val_type;
+                fn [<get_ $bitrange_name _max>](&self) -> $val_type;
             }
 
+            const _: () = assert!(
+                $crate::scattered::is_valid(&[$(($msb, $lsb)),+], $register::WIDTH),
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " has overlapping slices or doesn't fit in the register")
+            );
+            const _: () = assert!(
+                $crate::scattered::width(&[$(($msb, $lsb)),+]) <= <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+
+            $crate::field_info!($register, $bitrange_name, slices: [$(($msb, $lsb)),+], <$val_type>::MIN != 0);
+
             impl [<$register _ $bitrange_name>] for $register {
                 fn [<get_ $bitrange_name>](&self) -> $val_type {
-                    unsafe {
-                        let value = (self.register.as_mut().unwrap().get_range(BitRange {stop_bit: $msb, start_bit: $lsb }) as $val_type);
-                        value
-                    }
+                    const SLICES: &[(u32, u32)] = &[$(($msb, $lsb)),+];
+                    let value = $crate::scattered::gather(self.register.read().contents() as u128, SLICES);
+                    $crate::sign_extend!(value, $val_type, $crate::scattered::width(SLICES))
                 }
 
-                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self {
-                    unsafe {
-                        let comp = (value as $val_type);
-                        self.register.as_mut().unwrap().set_range(BitRange { stop_bit: $msb, start_bit: $lsb }, comp as RegisterType);
+                fn [<get_ $bitrange_name _min>](&self) -> $val_type {
+                    <$val_type>::MIN >> (<$val_type>::BITS - $crate::scattered::width(&[$(($msb, $lsb)),+]))
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> $val_type {
+                    <$val_type>::MAX >> (<$val_type>::BITS - $crate::scattered::width(&[$(($msb, $lsb)),+]))
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> Result<&mut Self, $crate::Errors<$val_type>> {
+                    let min = self.[<get_ $bitrange_name _min>]();
+                    let max = self.[<get_ $bitrange_name _max>]();
+                    if value < min {
+                        return Err($crate::Errors::TooLow { requested: value, min, max });
+                    }
+                    if value > max {
+                        return Err($crate::Errors::TooHigh { requested: value, min, max });
                     }
+                    self.modify(|register| {
+                        let contents = $crate::scattered::scatter(register.contents() as u128, &[$(($msb, $lsb)),+], value as u128);
+                        register.update(contents as _);
+                    });
+
+                    Ok(self)
+                }
+            }
+        }
+    };
+}
+
+/// Same as bitrange_scattered_raw!, but the field maps to an enum defined using
+/// bitrange_enum_values!, as with bitrange!.
+///
+/// ```ignore
+/// bitrange_scattered!(Config, mode, [(15, 15), (1, 0)], Mode);
+/// ```
+#[macro_export]
+macro_rules! bitrange_scattered {
+    ($register:ident, $bitrange_name:ident, [$(($msb:literal, $lsb:literal)),+], $val_type:ty) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> Option<$val_type>;
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self;
+            }
+
+            const _: () = assert!(
+                $crate::scattered::is_valid(&[$(($msb, $lsb)),+], $register::WIDTH),
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " has overlapping slices or doesn't fit in the register")
+            );
+
+            $crate::field_info!($register, $bitrange_name, slices: [$(($msb, $lsb)),+], false);
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name>](&self) -> Option<$val_type> {
+                    let val = $crate::scattered::gather(self.register.read().contents() as u128, &[$(($msb, $lsb)),+]);
+                    [<$val_type FromNum>](val as _)
+                }
 
+                fn [<set_ $bitrange_name>](&mut self, value: $val_type) -> &mut Self {
+                    let val = [<$val_type ToNum>](value) as u128;
+                    self.modify(|register| {
+                        let contents = $crate::scattered::scatter(register.contents() as u128, &[$(($msb, $lsb)),+], val);
+                        register.update(contents as _);
+                    });
                     self
                 }
             }
@@ -421,57 +3013,176 @@ macro_rules! bitrange_raw {
     };
 }
 
-/// Defines a bitrange and the correct getters and setters for the bitrange using
-/// a quantization value and a data type which quantizes the bitfield to an f32.
+/// Same as bitrange_scattered_raw!, but the concatenated raw value is quantized to
+/// an f32 as with bitrange_quantized!. The min and max are derived from the combined
+/// width, and the optional `offset = value` and `rounding = mode` are supported. The
+/// raw value is available through `get_<name>_raw` and `set_<name>_raw`.
 ///
-/// For example, on the Max17261, some 16 bit registers are two 8 bit registers that
-/// represent a real value such as voltage, temperate, etc. This macro allows
-/// named and range checked access to the register in human readable units.
-/// The macro requires a quantization value and the floating point min and max expected.
-/// This is used to test input values when the setter is called; if the setter value
-/// is outside the min / max range, the setter returns None, otherwise the returned
-/// value is the Some(quantized_value) that would be stored in the register.
+/// ```ignore
+/// bitrange_scattered_quantized!(Config, threshold, [(15, 12), (3, 0)], u8, 0.5, offset = 20.0);
+/// ```
 #[macro_export]
-macro_rules! bitrange_quantized {
-    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $quantization:expr, $min:expr, $max:expr) => {
-        paste! {
+macro_rules! bitrange_scattered_quantized {
+    ($register:ident, $bitrange_name:ident, [$(($msb:literal, $lsb:literal)),+], $val_type:ty, $quantization:expr $(, offset = $offset:expr)? $(, rounding = $rounding:ident)?) => {
+        $crate::paste::paste! {
+            $crate::bitrange_scattered_raw!($register, [<$bitrange_name _raw>], [$(($msb, $lsb)),+], $val_type);
+
             pub trait [<$register _ $bitrange_name>] {
                 fn [<get_ $bitrange_name>](&self) -> f32;
-                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Option<$val_type>;
-                fn [<get_ $bitrange_name _quatization>](&self) -> f32;
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors>;
+                fn [<get_ $bitrange_name _quantization>](&self) -> $crate::quantize::Quantization;
                 fn [<get_ $bitrange_name _min>](&self) -> f32;
                 fn [<get_ $bitrange_name _max>](&self) -> f32;
             }
 
+            impl $register {
+                #[allow(non_upper_case_globals)]
+                pub const $bitrange_name: $crate::FieldInfo = $crate::FieldInfo {
+                    name: stringify!($bitrange_name),
+                    ..$register::[<$bitrange_name _raw>]
+                };
+            }
+
             impl [<$register _ $bitrange_name>] for $register {
-                fn [<get_ $bitrange_name _quatization>](&self) -> f32 {
-                    $quantization
+                fn [<get_ $bitrange_name _quantization>](&self) -> $crate::quantize::Quantization {
+                    let field_min = self.[<get_ $bitrange_name _raw_min>]() as f32;
+                    let field_max = self.[<get_ $bitrange_name _raw_max>]() as f32;
+                    let offset = 0.0 $(+ ($offset as f32))?;
+                    let (min, max) = $crate::quantize::field_limits(field_min, field_max, $quantization as f32, offset);
+                    let rounding = $crate::Rounding::Nearest;
+                    $(let rounding = $crate::Rounding::$rounding;)?
+                    $crate::quantize::Quantization {
+                        step: $quantization as f32,
+                        offset,
+                        min,
+                        max,
+                        field_min,
+                        field_max,
+                        rounding,
+                    }
                 }
 
                 fn [<get_ $bitrange_name _min>](&self) -> f32 {
-                    $min
+                    self.[<get_ $bitrange_name _quantization>]().limits().0
                 }
 
-                fn [<get_ $bitrange_name _max>](&self) -> f32{
-                    $max
+                fn [<get_ $bitrange_name _max>](&self) -> f32 {
+                    self.[<get_ $bitrange_name _quantization>]().limits().1
                 }
 
                 fn [<get_ $bitrange_name>](&self) -> f32 {
-                    unsafe {
-                        let value = (self.register.as_mut().unwrap().get_range(BitRange {stop_bit: $msb, start_bit: $lsb }) as $val_type);
-                        (value as f32) * $quantization as f32
+                    self.[<get_ $bitrange_name _quantization>]().to_value(self.[<get_ $bitrange_name _raw>]() as f32)
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Result<f32, $crate::Errors> {
+                    let steps = self.[<get_ $bitrange_name _quantization>]().to_steps(value)?;
+                    // to_steps only returns values that fit in the field
+                    let _ = self.[<set_ $bitrange_name _raw>](steps as $val_type);
+                    Ok(self.[<get_ $bitrange_name>]())
+                }
+            }
+        }
+    };
+}
+
+/// Defines a bitrange with integer only getters and setters, for MCUs without
+/// an FPU. The LSB weight of the field is the rational `numerator / denominator`
+/// expressed in the output unit, for example the Max17261 VCell register is
+/// 78.125uV per LSB, so a numerator of 625 and denominator of 8 gives values in uV.
+///
+/// The output type, such as i32, is returned by the getter and taken by the setter.
+/// The min and max are in output units and checked exactly; if the setter value
+/// is outside the min / max range the setter returns Err(Errors::TooLow / TooHigh),
+/// otherwise the value is rounded to the nearest raw value and Ok(value) is returned
+/// with the value that was actually stored. The `_saturating` setter clamps instead.
+///
+/// As with bitrange_quantized!, an optional `offset = value` in output units can be
+/// added, giving raw * numerator / denominator + offset. `get_<name>_min` and
+/// `get_<name>_max` return the min / max narrowed to the raw range of the field plus
+/// the offset, the limits the setter checks against.
+#[macro_export]
+macro_rules! bitrange_fixed {
+    ($register:ident, $bitrange_name:ident, $msb:literal, $lsb:literal, $val_type:ty, $out_type:ty, $numerator:expr, $denominator:expr, $min:expr, $max:expr $(, offset = $offset:expr)?) => {
+        $crate::paste::paste! {
+            pub trait [<$register _ $bitrange_name>] {
+                fn [<get_ $bitrange_name>](&self) -> $out_type;
+                fn [<set_ $bitrange_name>](&mut self, value: $out_type) -> Result<$out_type, $crate::Errors<$out_type>>;
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: $out_type) -> $crate::Saturated<$out_type>;
+                fn [<get_ $bitrange_name _lsb>](&self) -> ($out_type, $out_type);
+                fn [<get_ $bitrange_name _offset>](&self) -> $out_type;
+                fn [<get_ $bitrange_name _min>](&self) -> $out_type;
+                fn [<get_ $bitrange_name _max>](&self) -> $out_type;
+            }
+
+            const _: () = assert!(
+                $msb - $lsb < <$val_type>::BITS,
+                concat!(stringify!($register), "::", stringify!($bitrange_name), " is wider than ", stringify!($val_type))
+            );
+
+            $crate::field_info!($register, $bitrange_name, $msb, $lsb, <$val_type>::MIN != 0);
+
+            impl [<$register _ $bitrange_name>] for $register {
+                fn [<get_ $bitrange_name _lsb>](&self) -> ($out_type, $out_type) {
+                    ($numerator, $denominator)
+                }
+
+                fn [<get_ $bitrange_name _offset>](&self) -> $out_type {
+                    0 $(+ $offset)?
+                }
+
+                fn [<get_ $bitrange_name _min>](&self) -> $out_type {
+                    // The configured min, raised to what fits in the field
+                    const SHIFT: u32 = <$val_type>::BITS - ($msb - $lsb + 1);
+                    let offset = self.[<get_ $bitrange_name _offset>]() as i64;
+                    let field_min = $crate::fixed::raw_to_scaled((<$val_type>::MIN >> SHIFT) as i64, $numerator as i64, $denominator as i64) + offset;
+                    let min: $out_type = $min;
+                    (min as i64).max(field_min) as $out_type
+                }
+
+                fn [<get_ $bitrange_name _max>](&self) -> $out_type {
+                    // The configured max, lowered to what fits in the field
+                    const SHIFT: u32 = <$val_type>::BITS - ($msb - $lsb + 1);
+                    let offset = self.[<get_ $bitrange_name _offset>]() as i64;
+                    let field_max = $crate::fixed::raw_to_scaled((<$val_type>::MAX >> SHIFT) as i64, $numerator as i64, $denominator as i64) + offset;
+                    let max: $out_type = $max;
+                    (max as i64).min(field_max) as $out_type
+                }
+
+                fn [<get_ $bitrange_name>](&self) -> $out_type {
+                    let value = $crate::sign_extend!(self.register.read().get_range($crate::BitRange {stop_bit: $msb, start_bit: $lsb }), $val_type, $msb - $lsb + 1);
+                    let offset = self.[<get_ $bitrange_name _offset>]() as i64;
+                    ($crate::fixed::raw_to_scaled(value as i64, $numerator as i64, $denominator as i64) + offset) as $out_type
+                }
+
+                fn [<set_ $bitrange_name>](&mut self, value: $out_type) -> Result<$out_type, $crate::Errors<$out_type>> {
+                    let offset = self.[<get_ $bitrange_name _offset>]() as i64;
+                    let min = self.[<get_ $bitrange_name _min>]();
+                    let max = self.[<get_ $bitrange_name _max>]();
+
+                    if value < min {
+                        return Err($crate::Errors::TooLow { requested: value, min, max });
+                    }
+                    if value > max {
+                        return Err($crate::Errors::TooHigh { requested: value, min, max });
                     }
+
+                    // With an LSB weight below one output unit the nearest raw value to the
+                    // field limit can be just outside of the field
+                    const SHIFT: u32 = <$val_type>::BITS - ($msb - $lsb + 1);
+                    let raw = $crate::fixed::scaled_to_raw(value as i64 - offset, $numerator as i64, $denominator as i64)
+                        .clamp((<$val_type>::MIN >> SHIFT) as i64, (<$val_type>::MAX >> SHIFT) as i64);
+                    self.modify(|register| { register.set_range($crate::BitRange { stop_bit: $msb, start_bit: $lsb }, raw as $val_type as _); });
+                    Ok(self.[<get_ $bitrange_name>]())
                 }
 
-                fn [<set_ $bitrange_name>](&mut self, value: f32) -> Option<$val_type> {
-                    if value < $min as f32 || value > $max {
-                        None
-                    }else{
-                        let quant_val = ((value / $quantization as f32) as $val_type);
-                        unsafe {
-                            self.register.as_mut().unwrap().set_range(BitRange { stop_bit: $msb, start_bit: $lsb }, quant_val as RegisterType);
-                        }
-                        Some(quant_val)
+                fn [<set_ $bitrange_name _saturating>](&mut self, value: $out_type) -> $crate::Saturated<$out_type> {
+                    match self.[<set_ $bitrange_name>](value) {
+                        Ok(value) => $crate::Saturated { value, clamped: false },
+                        Err($crate::Errors::TooLow { min: limit, .. }) | Err($crate::Errors::TooHigh { max: limit, .. }) => $crate::Saturated {
+                            value: self.[<set_ $bitrange_name>](limit).unwrap_or(limit),
+                            clamped: true,
+                        },
+                        Err($crate::Errors::QuantizationError) => unreachable!(),
                     }
                 }
             }
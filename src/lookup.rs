@@ -0,0 +1,102 @@
+//! Conversions used by the bitrange_table! and bitrange_piecewise! macros, for
+//! fields that don't scale linearly with the raw value.
+//!
+//! A table maps individual raw codes to values, for example the codes of the
+//! Max17261 HibCfg hib_enter_time to seconds. Breakpoints are `(raw, value)`
+//! points sorted by raw value, and raw values between two points are linearly
+//! interpolated, for example a thermistor curve.
+
+use crate::quantize::{round_steps, Rounding};
+use crate::Errors;
+
+/// Smallest and largest value in a table or list of breakpoints
+pub fn limits<T>(table: &[(T, f32)]) -> (f32, f32) {
+    table
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &(_, v)| {
+            (min.min(v), max.max(v))
+        })
+}
+
+fn check_limits<T>(table: &[(T, f32)], value: f32) -> Result<(), Errors> {
+    let (min, max) = limits(table);
+    if value.is_nan() {
+        Err(Errors::QuantizationError)
+    } else if value < min {
+        Err(Errors::TooLow {
+            requested: value,
+            min,
+            max,
+        })
+    } else if value > max {
+        Err(Errors::TooHigh {
+            requested: value,
+            min,
+            max,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Value of a raw code, None if the code isn't in the table
+pub fn table_value<T: Copy + PartialEq>(table: &[(T, f32)], raw: T) -> Option<f32> {
+    table.iter().find(|&&(r, _)| r == raw).map(|&(_, v)| v)
+}
+
+/// Code of the table entry nearest to `value`. Values outside of the smallest
+/// and largest value in the table are an error.
+pub fn table_raw<T: Copy>(table: &[(T, f32)], value: f32) -> Result<T, Errors> {
+    check_limits(table, value)?;
+    let mut nearest = table[0];
+    for &entry in table {
+        if (entry.1 - value).abs() < (nearest.1 - value).abs() {
+            nearest = entry;
+        }
+    }
+    Ok(nearest.0)
+}
+
+/// Linearly interpolated value of a raw value, None if the raw value is outside
+/// of the breakpoints
+pub fn piecewise_value<T: Copy + Into<i128>>(points: &[(T, f32)], raw: T) -> Option<f32> {
+    let raw = raw.into();
+    points.windows(2).find_map(|segment| {
+        let (r0, v0) = (segment[0].0.into(), segment[0].1);
+        let (r1, v1) = (segment[1].0.into(), segment[1].1);
+        if r0 <= raw && raw <= r1 {
+            if r0 == r1 {
+                return Some(v0);
+            }
+            Some(v0 + (v1 - v0) * (raw - r0) as f32 / (r1 - r0) as f32)
+        } else {
+            None
+        }
+    })
+}
+
+/// Raw value, rounded to the nearest step, that interpolates to `value`. The
+/// value may increase or decrease along the breakpoints, the first segment
+/// containing the value is used. Values outside of the breakpoints are an error.
+pub fn piecewise_raw<T: Copy + Into<i128>>(points: &[(T, f32)], value: f32) -> Result<f32, Errors> {
+    check_limits(points, value)?;
+    points
+        .windows(2)
+        .find_map(|segment| {
+            let (r0, v0) = (segment[0].0.into() as f32, segment[0].1);
+            let (r1, v1) = (segment[1].0.into() as f32, segment[1].1);
+            if v0.min(v1) <= value && value <= v0.max(v1) {
+                let raw = if v0 == v1 {
+                    r0
+                } else {
+                    r0 + (value - v0) * (r1 - r0) / (v1 - v0)
+                };
+                round_steps(raw, Rounding::Nearest).ok()
+            } else {
+                None
+            }
+        })
+        // A single breakpoint has no segments
+        .or_else(|| points.first().map(|&(r, _)| r.into() as f32))
+        .ok_or(Errors::QuantizationError)
+}
@@ -0,0 +1,149 @@
+//! Storage of the registers of a peripheral. A peripheral normally caches its
+//! registers in RAM, in an array indexed by the register index. An on-chip
+//! peripheral declared with `storage: MemoryMapped` instead accesses its registers
+//! in place, at the base address of the peripheral plus the register address, so
+//! the same register and field macros work on memory mapped registers.
+//!
+//! Registers are read and written by value through a `BackerPtr`, so no reference
+//! to a memory mapped register is ever made. Memory mapped registers are accessed
+//! with volatile reads and writes of their own width, which may be narrower than the
+//! backer, and read-modify-written once per field update.
+//! As on a bus, W1C fields are written as 0 and W0C fields as 1 unless they are
+//! acknowledged.
+
+use core::cell::UnsafeCell;
+
+use crate::Backer;
+
+/// Where the backing register at `index`, and at byte offset `address`, is stored.
+/// `width` is the width of the register in bits.
+pub trait Storage<B> {
+    fn backer(&self, index: usize, address: usize, width: u32) -> BackerPtr<B>;
+}
+
+/// True if a register `width` bits wide at `address`, and repeated every `stride`
+/// bytes in an array, can be accessed in place: as a u8 to u128 at an address
+/// aligned to its width.
+pub const fn mappable(width: u32, address: usize, stride: usize) -> bool {
+    let bytes = width as usize / 8;
+    matches!(width, 8 | 16 | 32 | 64 | 128)
+        && address.is_multiple_of(bytes)
+        && stride.is_multiple_of(bytes)
+}
+
+/// Registers cached in RAM. The registers are written through the shared
+/// peripheral by the register accessors, so they are kept in an UnsafeCell.
+pub struct Cached<B, const N: usize> {
+    registers: UnsafeCell<[B; N]>,
+}
+
+impl<B, const N: usize> Cached<B, N> {
+    pub fn new(registers: [B; N]) -> Self {
+        Cached {
+            registers: UnsafeCell::new(registers),
+        }
+    }
+}
+
+impl<B, const N: usize> Storage<B> for Cached<B, N> {
+    fn backer(&self, index: usize, _address: usize, _width: u32) -> BackerPtr<B> {
+        assert!(index < N, "Register index must be within the peripheral");
+        BackerPtr {
+            register: self.registers.get().cast::<B>().wrapping_add(index),
+            width: None,
+        }
+    }
+}
+
+/// Registers at a base address, such as an on-chip UART at 0x4000_1000. Each
+/// register is at the base address plus its address in bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryMapped {
+    base: *mut u8,
+}
+
+impl MemoryMapped {
+    /// # Safety
+    ///
+    /// `base` must point to memory, such as the registers of a device or a buffer
+    /// in tests, that is valid for every register of the peripheral at its address,
+    /// and aligned so that every register is aligned to its width.
+    pub unsafe fn new(base: *mut u8) -> Self {
+        MemoryMapped { base }
+    }
+
+    pub fn base(&self) -> *mut u8 {
+        self.base
+    }
+}
+
+impl<B> Storage<B> for MemoryMapped {
+    fn backer(&self, _index: usize, address: usize, width: u32) -> BackerPtr<B> {
+        BackerPtr {
+            register: self.base.wrapping_add(address) as *mut B,
+            width: Some(width),
+        }
+    }
+}
+
+/// Pointer to a backing register in a peripheral's storage, held by the register
+/// structs. The register is copied out, changed and copied back rather than
+/// borrowed.
+pub struct BackerPtr<B> {
+    register: *mut B,
+    // Width of a memory mapped register in bits, None if it is cached
+    width: Option<u32>,
+}
+
+impl<B> Clone for BackerPtr<B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B> Copy for BackerPtr<B> {}
+
+impl<B: Backer> BackerPtr<B> {
+    /// True if the register is accessed in place, so that writes reach the device
+    pub fn is_memory_mapped(&self) -> bool {
+        self.width.is_some()
+    }
+
+    pub fn read(&self) -> B {
+        // The storage the pointer was made from guarantees it is valid and aligned.
+        // A memory mapped register is read at its own width, so a register narrower
+        // than the backer doesn't read its neighbours
+        unsafe {
+            match self.width {
+                None => self.register.read(),
+                Some(8) => B::from_bits(self.register.cast::<u8>().read_volatile() as u128),
+                Some(16) => B::from_bits(self.register.cast::<u16>().read_volatile() as u128),
+                Some(32) => B::from_bits(self.register.cast::<u32>().read_volatile() as u128),
+                Some(64) => B::from_bits(self.register.cast::<u64>().read_volatile() as u128),
+                Some(_) => B::from_bits(self.register.cast::<u128>().read_volatile()),
+            }
+        }
+    }
+
+    pub fn write(&self, register: B) {
+        let bits = register.bits();
+        unsafe {
+            match self.width {
+                None => self.register.write(register),
+                Some(8) => self.register.cast::<u8>().write_volatile(bits as u8),
+                Some(16) => self.register.cast::<u16>().write_volatile(bits as u16),
+                Some(32) => self.register.cast::<u32>().write_volatile(bits as u32),
+                Some(64) => self.register.cast::<u64>().write_volatile(bits as u64),
+                Some(_) => self.register.cast::<u128>().write_volatile(bits),
+            }
+        }
+    }
+
+    /// Reads the register, changes it with `f` and writes it back
+    pub fn modify<R>(&self, f: impl FnOnce(&mut B) -> R) -> R {
+        let mut register = self.read();
+        let result = f(&mut register);
+        self.write(register);
+        result
+    }
+}
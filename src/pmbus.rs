@@ -0,0 +1,137 @@
+//! PMBus numeric formats used by the bitrange_linear11!, bitrange_linear16! and
+//! bitrange_direct! macros.
+//!
+//! - LINEAR11: a 16 bit word with a 5 bit two's complement exponent `N` in the
+//!   upper bits and an 11 bit two's complement mantissa `Y`, value = Y * 2^N
+//! - LINEAR16: a 16 bit unsigned mantissa `V` with the exponent `N` taken from the
+//!   VOUT_MODE command, value = V * 2^N
+//! - DIRECT: a 16 bit two's complement value `Y` with coefficients `m`, `b` and
+//!   `R`, value = (Y * 10^-R - b) / m
+
+use crate::quantize::{round_steps, Rounding};
+use crate::Errors;
+
+const LINEAR11_EXPONENT_MIN: i8 = -16;
+const LINEAR11_EXPONENT_MAX: i8 = 15;
+const LINEAR11_MANTISSA_MIN: i32 = -1024;
+const LINEAR11_MANTISSA_MAX: i32 = 1023;
+
+/// 2^exponent, without `f32::powi` which isn't available in `no_std`
+pub fn pow2(exponent: i8) -> f32 {
+    let mut value = 1.0;
+    for _ in 0..exponent.unsigned_abs() {
+        value *= 2.0;
+    }
+    if exponent < 0 {
+        1.0 / value
+    } else {
+        value
+    }
+}
+
+/// 10^exponent, without `f32::powi` which isn't available in `no_std`
+pub fn pow10(exponent: i8) -> f32 {
+    let mut value = 1.0;
+    for _ in 0..exponent.unsigned_abs() {
+        value *= 10.0;
+    }
+    if exponent < 0 {
+        1.0 / value
+    } else {
+        value
+    }
+}
+
+/// NaN can't be encoded, and infinity is out of range
+fn check_finite(value: f32, min: f32, max: f32) -> Result<(), Errors> {
+    if value.is_nan() {
+        Err(Errors::QuantizationError)
+    } else if value.is_infinite() {
+        Err(range_error(value, min, max))
+    } else {
+        Ok(())
+    }
+}
+
+fn range_error(value: f32, min: f32, max: f32) -> Errors {
+    if value < min {
+        Errors::TooLow {
+            requested: value,
+            min,
+            max,
+        }
+    } else {
+        Errors::TooHigh {
+            requested: value,
+            min,
+            max,
+        }
+    }
+}
+
+pub fn linear11_to_f32(raw: u16) -> f32 {
+    let exponent = (raw as i16 >> 11) as i8;
+    let mantissa = (raw << 5) as i16 >> 5;
+    mantissa as f32 * pow2(exponent)
+}
+
+/// Encodes a value as LINEAR11, using the smallest exponent that fits the
+/// mantissa so that as much precision as possible is kept.
+pub fn f32_to_linear11(value: f32) -> Result<u16, Errors> {
+    let scale = pow2(LINEAR11_EXPONENT_MAX);
+    let min = LINEAR11_MANTISSA_MIN as f32 * scale;
+    let max = LINEAR11_MANTISSA_MAX as f32 * scale;
+    check_finite(value, min, max)?;
+
+    for exponent in LINEAR11_EXPONENT_MIN..=LINEAR11_EXPONENT_MAX {
+        let mantissa = round_steps(value / pow2(exponent), Rounding::Nearest)?;
+        if (LINEAR11_MANTISSA_MIN as f32..=LINEAR11_MANTISSA_MAX as f32).contains(&mantissa) {
+            return Ok(((exponent as u16) << 11) | (mantissa as i16 as u16 & 0x07FF));
+        }
+    }
+
+    Err(range_error(value, min, max))
+}
+
+/// Exponent of LINEAR16 values from the VOUT_MODE byte, None if VOUT_MODE
+/// doesn't select the linear format
+pub fn vout_mode_exponent(vout_mode: u8) -> Option<i8> {
+    if vout_mode >> 5 == 0 {
+        Some(((vout_mode << 3) as i8) >> 3)
+    } else {
+        None
+    }
+}
+
+pub fn linear16_to_f32(raw: u16, exponent: i8) -> f32 {
+    raw as f32 * pow2(exponent)
+}
+
+pub fn f32_to_linear16(value: f32, exponent: i8) -> Result<u16, Errors> {
+    let scale = pow2(exponent);
+    check_finite(value, 0.0, u16::MAX as f32 * scale)?;
+    let mantissa = round_steps(value / scale, Rounding::Nearest)?;
+    if (0.0..=u16::MAX as f32).contains(&mantissa) {
+        Ok(mantissa as u16)
+    } else {
+        Err(range_error(value, 0.0, u16::MAX as f32 * scale))
+    }
+}
+
+pub fn direct_to_f32(raw: i16, m: i16, b: i16, r: i8) -> f32 {
+    (raw as f32 * pow10(-r) - b as f32) / m as f32
+}
+
+pub fn f32_to_direct(value: f32, m: i16, b: i16, r: i8) -> Result<i16, Errors> {
+    let low = direct_to_f32(i16::MIN, m, b, r);
+    let high = direct_to_f32(i16::MAX, m, b, r);
+    let (min, max) = (low.min(high), low.max(high));
+    check_finite(value, min, max)?;
+
+    let raw = round_steps((m as f32 * value + b as f32) * pow10(r), Rounding::Nearest)?;
+    if (i16::MIN as f32..=i16::MAX as f32).contains(&raw) {
+        Ok(raw as i16)
+    } else {
+        Err(range_error(value, min, max))
+    }
+}
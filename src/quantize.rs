@@ -0,0 +1,222 @@
+//! Rounding and range checking used by the bitrange_quantized! and
+//! bitrange_quantity! macros. These are written without `f32::round` and
+//! friends, which are not available in `no_std`.
+
+use core::ops::{Add, Div, Mul, Sub};
+
+use crate::Errors;
+
+/// The float types a quantization is computed in. Fields of one register use f32,
+/// and fields split across registers use f64 so that raw values wider than the 24
+/// bit mantissa of an f32 still map to whole steps.
+pub trait Float:
+    Copy
+    + PartialOrd
+    + From<f32>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+    const EPSILON: Self;
+
+    fn is_finite(self) -> bool;
+    fn is_nan(self) -> bool;
+    fn abs(self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    /// Rounds towards zero
+    fn trunc(self) -> Self;
+}
+
+macro_rules! float {
+    ($type:ty) => {
+        impl Float for $type {
+            const EPSILON: Self = <$type>::EPSILON;
+
+            fn is_finite(self) -> bool {
+                <$type>::is_finite(self)
+            }
+
+            fn is_nan(self) -> bool {
+                <$type>::is_nan(self)
+            }
+
+            fn abs(self) -> Self {
+                <$type>::abs(self)
+            }
+
+            fn min(self, other: Self) -> Self {
+                <$type>::min(self, other)
+            }
+
+            fn max(self, other: Self) -> Self {
+                <$type>::max(self, other)
+            }
+
+            fn trunc(self) -> Self {
+                // From 2^(MANTISSA_DIGITS - 1) on every value is already whole, and
+                // may not fit in an i64
+                if <$type>::abs(self) >= (1u64 << (<$type>::MANTISSA_DIGITS - 1)) as $type {
+                    self
+                } else {
+                    self as i64 as $type
+                }
+            }
+        }
+    };
+}
+
+float!(f32);
+float!(f64);
+
+/// How a `set_` value between two quantization steps is stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Nearest step, ties away from zero
+    #[default]
+    Nearest,
+    /// Next step towards negative infinity
+    Floor,
+    /// Next step towards positive infinity
+    Ceil,
+    /// Only values that fall on a step are accepted, anything else is an
+    /// `Errors::QuantizationError`
+    Exact,
+}
+
+fn floor<F: Float>(x: F) -> F {
+    let t = x.trunc();
+    if t > x {
+        t - F::from(1.0)
+    } else {
+        t
+    }
+}
+
+fn ceil<F: Float>(x: F) -> F {
+    let t = x.trunc();
+    if t < x {
+        t + F::from(1.0)
+    } else {
+        t
+    }
+}
+
+fn nearest<F: Float>(x: F) -> F {
+    if x < F::from(0.0) {
+        ceil(x - F::from(0.5))
+    } else {
+        floor(x + F::from(0.5))
+    }
+}
+
+/// Rounds a number of quantization steps to a whole number of steps.
+///
+/// Dividing by a quantization such as 0.02 is rarely exact in floating point,
+/// so 3.98 / 0.02 may be 198.99998. Values within a few ULPs of a step are
+/// treated as being on that step for every rounding mode. NaN and infinity
+/// aren't on any step and return `Errors::QuantizationError`.
+pub fn round_steps<F: Float>(steps: F, rounding: Rounding) -> Result<F, Errors<F>> {
+    if !steps.is_finite() {
+        return Err(Errors::QuantizationError);
+    }
+
+    let near = nearest(steps);
+    if (steps - near).abs() <= F::from(8.0) * F::EPSILON * steps.abs().max(F::from(1.0)) {
+        return Ok(near);
+    }
+
+    match rounding {
+        Rounding::Nearest => Ok(near),
+        Rounding::Floor => Ok(floor(steps)),
+        Rounding::Ceil => Ok(ceil(steps)),
+        Rounding::Exact => Err(Errors::QuantizationError),
+    }
+}
+
+/// Smallest and largest physical value of a field holding `field_min..=field_max`
+/// steps, used by bitrange_quantized! to derive the min / max at compile time
+pub const fn field_limits(field_min: f32, field_max: f32, step: f32, offset: f32) -> (f32, f32) {
+    let a = field_min * step + offset;
+    let b = field_max * step + offset;
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Describes how a quantized field maps raw steps to physical values,
+/// `value = steps * step + offset`. `field_min` and `field_max` are the
+/// smallest and largest number of steps the bits of the field can hold.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quantization<F = f32> {
+    pub step: F,
+    pub offset: F,
+    pub min: F,
+    pub max: F,
+    pub field_min: F,
+    pub field_max: F,
+    pub rounding: Rounding,
+}
+
+impl<F: Float> Quantization<F> {
+    /// The configured min / max, narrowed to what fits in the field
+    pub fn limits(&self) -> (F, F) {
+        (
+            self.min.max(self.field_min * self.step + self.offset),
+            self.max.min(self.field_max * self.step + self.offset),
+        )
+    }
+
+    pub fn to_value(&self, steps: F) -> F {
+        steps * self.step + self.offset
+    }
+
+    /// Converts a physical value to a whole number of steps that fits in the field
+    /// and, once rounded, is still within the limits
+    pub fn to_steps(&self, value: F) -> Result<F, Errors<F>> {
+        let (min, max) = self.limits();
+        let too_low = Errors::TooLow {
+            requested: value,
+            min,
+            max,
+        };
+        let too_high = Errors::TooHigh {
+            requested: value,
+            min,
+            max,
+        };
+
+        // NaN passes both range checks below
+        if value.is_nan() {
+            return Err(Errors::QuantizationError);
+        }
+        if value < min {
+            return Err(too_low);
+        }
+        if value > max {
+            return Err(too_high);
+        }
+
+        // Floor, Ceil and Nearest can step just outside of the limits, compare whole
+        // steps so that a limit on a step isn't rejected for floating point error
+        let a = (min - self.offset) / self.step;
+        let b = (max - self.offset) / self.step;
+        let low = round_steps(a.min(b), Rounding::Ceil)?.max(self.field_min);
+        let high = round_steps(a.max(b), Rounding::Floor)?.min(self.field_max);
+
+        let steps = round_steps((value - self.offset) / self.step, self.rounding)?;
+        if steps < low || steps > high {
+            // With a negative step the smallest number of steps is the largest value
+            if (steps < low) == (self.step > F::from(0.0)) {
+                Err(too_low)
+            } else {
+                Err(too_high)
+            }
+        } else {
+            Ok(steps)
+        }
+    }
+}
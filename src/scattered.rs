@@ -0,0 +1,84 @@
+//! Bit gathering used by the bitrange_scattered! macros, for fields stored in
+//! several non-contiguous bit slices of one register. Slices are `(msb, lsb)`
+//! pairs listed from the most significant part of the value to the least
+//! significant, for example `[(15, 12), (3, 0)]` for an 8 bit value whose upper
+//! nibble is in bits 15..12 and lower nibble in bits 3..0.
+
+const fn slice_mask(msb: u32, lsb: u32) -> u128 {
+    (u128::MAX >> (127 - (msb - lsb))) << lsb
+}
+
+/// Total width of the slices in bits
+pub const fn width(slices: &[(u32, u32)]) -> u32 {
+    let mut width = 0;
+    let mut i = 0;
+    while i < slices.len() {
+        width += slices[i].0 - slices[i].1 + 1;
+        i += 1;
+    }
+    width
+}
+
+/// Bits of the register occupied by the slices
+pub const fn mask(slices: &[(u32, u32)]) -> u128 {
+    let mut mask = 0;
+    let mut i = 0;
+    while i < slices.len() {
+        mask |= slice_mask(slices[i].0, slices[i].1);
+        i += 1;
+    }
+    mask
+}
+
+/// Highest and lowest bit of the slices
+pub const fn span(slices: &[(u32, u32)]) -> (u32, u32) {
+    let (mut msb, mut lsb) = (0, u32::MAX);
+    let mut i = 0;
+    while i < slices.len() {
+        if slices[i].0 > msb {
+            msb = slices[i].0;
+        }
+        if slices[i].1 < lsb {
+            lsb = slices[i].1;
+        }
+        i += 1;
+    }
+    (msb, lsb)
+}
+
+/// True if no two slices share a bit and every slice fits in `bits`
+pub const fn is_valid(slices: &[(u32, u32)], bits: u32) -> bool {
+    let mut i = 0;
+    while i < slices.len() {
+        let (msb, lsb) = slices[i];
+        if msb < lsb || msb >= bits {
+            return false;
+        }
+        let mut j = i + 1;
+        while j < slices.len() {
+            if slices[j].0 >= lsb && slices[j].1 <= msb {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Concatenates the slices of `contents` into one value
+pub fn gather(contents: u128, slices: &[(u32, u32)]) -> u128 {
+    slices.iter().fold(0, |value, &(msb, lsb)| {
+        (value << (msb - lsb + 1)) | ((contents & slice_mask(msb, lsb)) >> lsb)
+    })
+}
+
+/// Spreads `value` over the slices of `contents`, leaving the other bits unchanged
+pub fn scatter(contents: u128, slices: &[(u32, u32)], value: u128) -> u128 {
+    let mut remaining = width(slices);
+    slices.iter().fold(contents, |contents, &(msb, lsb)| {
+        remaining -= msb - lsb + 1;
+        let mask = slice_mask(msb, lsb);
+        (contents & !mask) | (((value >> remaining) << lsb) & mask)
+    })
+}
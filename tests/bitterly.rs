@@ -1,9 +1,14 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+// The original tests predate these lints
+#![allow(unused_imports, unused_mut, unused_variables, unused_assignments)]
+#![allow(clippy::bool_assert_comparison, clippy::unnecessary_cast)]
+
 #[cfg(test)]
 mod tests {
     #[test]
     fn register_test() {
         use bitterly::register_backer;
-        use paste::paste;
 
         register_backer!(Register, u8);
 
@@ -87,7 +92,6 @@ mod tests {
     #[test]
     pub fn bitfield_test() {
         use bitterly::{bitfield, peripheral, register, register_backer};
-        use paste::paste;
 
         register_backer!(Register, u8);
 
@@ -140,7 +144,6 @@ mod tests {
     #[test]
     pub fn bitrange_enum_test() {
         use bitterly::{bitfield, peripheral, register, register_backer};
-        use paste::paste;
 
         register_backer!(Register, u8);
 
@@ -166,7 +169,6 @@ mod tests {
         use bitterly::{
             bitfield, bitrange, bitrange_enum_values, peripheral, register, register_backer,
         };
-        use paste::paste;
 
         register_backer!(Register, u8);
 
@@ -232,7 +234,6 @@ mod tests {
             bitfield, bitrange, bitrange_enum_values, bitrange_quantized, bitrange_raw, peripheral,
             register, register_backer, Errors,
         };
-        use paste::paste;
 
         register_backer!(Register, u16);
 
@@ -256,19 +257,19 @@ mod tests {
         bitrange_quantized!(MaxMinVolt, MinVCell, 7, 0, u8, 0.02, 0.0, 255.0 * 0.02); // 20mv resolution
 
         let mut result = max17261.MaxMinVolt().set_MaxVCell(4.0);
-        assert!(result.is_some());
+        assert!(result.is_ok());
         let mut max_vcell = max17261.MaxMinVolt().get_MaxVCell();
         assert_eq!(max_vcell, 4.0);
 
         result = max17261.MaxMinVolt().set_MinVCell(-1.0);
-        assert!(result.is_none());
+        assert!(result.is_err());
 
         result = max17261.MaxMinVolt().set_MinVCell(u8::MAX as f32 * 0.02);
         max_vcell = max17261.MaxMinVolt().get_MinVCell();
         assert_eq!(max_vcell, u8::MAX as f32 * 0.02);
 
         result = max17261.MaxMinVolt().set_MinVCell(256 as f32 * 0.020);
-        assert!(result.is_none());
+        assert!(result.is_err());
 
         register!(MaxMinTemp);
         bitrange_quantized!(MaxMinTemp, MaxTemp, 15, 8, i8, 1.0, -128.0, 127.0); // 1/256 Celcius resolution
@@ -283,22 +284,22 @@ mod tests {
         assert_eq!(min_temp, -128.0);
 
         i8_result = max17261.MaxMinTemp().set_MinTemp(-129.0);
-        assert!(i8_result.is_none());
+        assert!(i8_result.is_err());
 
         i8_result = max17261.MaxMinTemp().set_MinTemp(127.0);
         min_temp = max17261.MaxMinTemp().get_MinTemp();
         assert_eq!(min_temp, 127.0);
 
         i8_result = max17261.MaxMinTemp().set_MinTemp(128.0);
-        assert!(i8_result.is_none());
+        assert!(i8_result.is_err());
 
         i8_result = max17261.MaxMinTemp().set_MaxTemp(128.0);
-        assert!(i8_result.is_none());
+        assert!(i8_result.is_err());
 
         let msb = max17261.MaxMinTemp().set_MaxTemp(127.0).unwrap();
         let lsb = max17261.MaxMinTemp().set_MinTemp(-128.0).unwrap();
 
-        let reconstructed_value = u16::from_le_bytes([lsb as u8, msb as u8]);
+        let reconstructed_value = u16::from_le_bytes([lsb as i8 as u8, msb as i8 as u8]);
         let raw_register = max17261.MaxMinTemp().contents();
         assert_eq!(reconstructed_value, raw_register);
 
@@ -306,22 +307,22 @@ mod tests {
         bitrange_raw!(I8TestRegister, I8Upper, 15, 8, i8);
         bitrange_raw!(I8TestRegister, I8Lower, 7, 0, i8);
 
-        max17261.I8TestRegister().set_I8Upper(127);
+        max17261.I8TestRegister().set_I8Upper(127).unwrap();
         let mut i8_upper = max17261.I8TestRegister().get_I8Upper();
         assert_eq!(i8_upper, 127);
 
-        max17261.I8TestRegister().set_I8Lower(-128);
+        max17261.I8TestRegister().set_I8Lower(-128).unwrap();
         let mut i8_lower = max17261.I8TestRegister().get_I8Lower();
         assert_eq!(i8_lower, -128);
 
         register!(I16TestRegister);
         bitrange_raw!(I16TestRegister, SignedValue, 15, 0, i16);
 
-        max17261.I16TestRegister().set_SignedValue(-32768);
+        max17261.I16TestRegister().set_SignedValue(-32768).unwrap();
         let mut signed_value = max17261.I16TestRegister().get_SignedValue();
         assert_eq!(signed_value, -32768);
 
-        max17261.I16TestRegister().set_SignedValue(32767);
+        max17261.I16TestRegister().set_SignedValue(32767).unwrap();
         signed_value = max17261.I16TestRegister().get_SignedValue();
         assert_eq!(signed_value, 32767);
     }
@@ -332,7 +333,6 @@ mod tests {
             bitfield, bitrange, bitrange_enum_values, bitrange_raw, peripheral, register,
             register_backer,
         };
-        use paste::paste;
 
         // Create a u8 Register
         register_backer!(Register, u8);
@@ -512,4 +512,2329 @@ mod tests {
 
         assert_eq!(new_address, 0x6B);
     }
+
+    #[test]
+    fn console_test() {
+        use bitterly::console::{execute, Console, ConsoleError};
+        use bitterly::{bitfield, bitrange_raw, peripheral, register, register_backer, Peripheral};
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            3,
+            [
+                (Status, 0x00, 0),
+                (SAlrtTh, 0x03, 1),
+                (ScOcvLim, 0xD1, 2)
+            ],
+            fields: [
+                (Status, [br, por]),
+                (ScOcvLim, [ocv_low_lim, ocv_delta])
+            ]
+        );
+
+        register!(Status);
+        bitfield!(Status, br, 15);
+        bitfield!(Status, por, 1);
+
+        register!(SAlrtTh);
+
+        register!(ScOcvLim);
+        bitrange_raw!(ScOcvLim, ocv_low_lim, 15, 7, u16);
+        bitrange_raw!(ScOcvLim, ocv_delta, 6, 0, i8);
+
+        let mut max17261 = Max17261::new();
+        assert_eq!(max17261.registers().len(), 3);
+        assert_eq!(max17261.fields(0), &[Status::br, Status::por]);
+        assert!(max17261.fields(1).is_empty());
+
+        let mut out = String::new();
+        execute(&mut max17261, "list", &mut out).unwrap();
+        assert_eq!(out, "Status @ 0x00\nSAlrtTh @ 0x03\nScOcvLim @ 0xD1\n");
+
+        out.clear();
+        execute(&mut max17261, "list Status", &mut out).unwrap();
+        assert_eq!(out, "Status.br [15:15]\nStatus.por [1:1]\n");
+
+        out.clear();
+        execute(&mut max17261, "write SAlrtTh 0xFF00", &mut out).unwrap();
+        assert_eq!(out, "SAlrtTh @ 0x03 = 0xFF00\n");
+        assert_eq!(max17261.SAlrtTh().contents(), 0xFF00);
+
+        // Registers can be referenced by address
+        out.clear();
+        execute(&mut max17261, "read 0x03", &mut out).unwrap();
+        assert_eq!(out, "SAlrtTh @ 0x03 = 0xFF00\n");
+
+        out.clear();
+        execute(&mut max17261, "set Status.por 1", &mut out).unwrap();
+        assert_eq!(out, "Status.por = 1\n");
+        assert!(max17261.Status().get_por());
+        assert_eq!(max17261.Status().contents(), 0x0002);
+
+        out.clear();
+        execute(&mut max17261, "set ScOcvLim.ocv_delta -3", &mut out).unwrap();
+        assert_eq!(out, "ScOcvLim.ocv_delta = -3\n");
+        assert_eq!(max17261.ScOcvLim().contents(), 0x007D);
+
+        // Hex and binary values are raw encodings, with either case of prefix
+        out.clear();
+        execute(&mut max17261, "set ScOcvLim.ocv_delta 0X7E", &mut out).unwrap();
+        execute(&mut max17261, "set ScOcvLim.ocv_delta 0B1111101", &mut out).unwrap();
+        assert_eq!(out, "ScOcvLim.ocv_delta = -2\nScOcvLim.ocv_delta = -3\n");
+
+        out.clear();
+        execute(&mut max17261, "set ScOcvLim.ocv_low_lim 511", &mut out).unwrap();
+        execute(&mut max17261, "get ScOcvLim.ocv_low_lim", &mut out).unwrap();
+        assert_eq!(
+            out,
+            "ScOcvLim.ocv_low_lim = 511\nScOcvLim.ocv_low_lim = 511\n"
+        );
+        assert_eq!(max17261.ScOcvLim().contents(), 0xFFFD);
+
+        assert_eq!(
+            execute(&mut max17261, "set ScOcvLim.ocv_low_lim 512", &mut out),
+            Err(ConsoleError::InvalidValue)
+        );
+        assert_eq!(
+            execute(&mut max17261, "set ScOcvLim.ocv_delta -65", &mut out),
+            Err(ConsoleError::InvalidValue)
+        );
+        assert_eq!(
+            execute(&mut max17261, "write SAlrtTh 0x10000", &mut out),
+            Err(ConsoleError::InvalidValue)
+        );
+        assert_eq!(
+            execute(&mut max17261, "read Config", &mut out),
+            Err(ConsoleError::UnknownRegister)
+        );
+        assert_eq!(
+            execute(&mut max17261, "get Status.dnr", &mut out),
+            Err(ConsoleError::UnknownField)
+        );
+        assert_eq!(
+            execute(&mut max17261, "erase", &mut out),
+            Err(ConsoleError::UnknownCommand)
+        );
+        assert_eq!(
+            execute(&mut max17261, "write SAlrtTh", &mut out),
+            Err(ConsoleError::MissingArgument)
+        );
+
+        out.clear();
+        execute(&mut max17261, "dump", &mut out).unwrap();
+        assert_eq!(
+            out,
+            "Status @ 0x00 = 0x0002\nSAlrtTh @ 0x03 = 0xFF00\nScOcvLim @ 0xD1 = 0xFFFD\n"
+        );
+
+        // Buffered console, as it would be fed from a UART
+        let mut console: Console<32> = Console::new();
+        out.clear();
+        for byte in b"write Status 0x8000\r\nbogus\n" {
+            console.feed(*byte, &mut max17261, &mut out);
+        }
+        assert_eq!(out, "Status @ 0x00 = 0x8000\nerror: unknown command\n");
+        assert!(max17261.Status().get_br());
+
+        let mut console: Console<4> = Console::new();
+        out.clear();
+        let mut result = None;
+        for byte in b"dump dump\n" {
+            result = console.feed(*byte, &mut max17261, &mut out);
+        }
+        assert_eq!(result, Some(Err(ConsoleError::LineTooLong)));
+    }
+
+    #[test]
+    fn bitrange_fixed_test() {
+        use bitterly::{bitrange_fixed, peripheral, register, register_backer, Errors};
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            3,
+            [(VCell, 0x09, 0), (Current, 0x0A, 1), (MaxMinTemp, 0x1A, 2)]
+        );
+
+        let max17261 = Max17261::new();
+
+        // 78.125uV per LSB, in uV
+        register!(VCell);
+        bitrange_fixed!(VCell, voltage, 15, 0, u16, i32, 625, 8, 0, 5_119_921);
+
+        assert_eq!(max17261.VCell().get_voltage_lsb(), (625, 8));
+        assert_eq!(max17261.VCell().set_voltage(3_700_000), Ok(3_700_000));
+        assert_eq!(max17261.VCell().contents(), 47360);
+        assert_eq!(max17261.VCell().get_voltage(), 3_700_000);
+
+        // Values between LSBs are rounded to the nearest raw value
+        assert_eq!(max17261.VCell().set_voltage(3_700_040), Ok(3_700_078));
+        assert_eq!(max17261.VCell().get_voltage(), 3_700_078);
+        assert_eq!(max17261.VCell().set_voltage(3_700_038), Ok(3_700_000));
+
+        assert_eq!(max17261.VCell().set_voltage(5_119_921), Ok(5_119_922));
+        assert_eq!(max17261.VCell().get_voltage(), 5_119_922);
+        assert_eq!(
+            max17261.VCell().set_voltage(5_119_922),
+            Err(Errors::TooHigh {
+                requested: 5_119_922,
+                min: 0,
+                max: 5_119_921
+            })
+        );
+        assert_eq!(
+            max17261.VCell().set_voltage(-1),
+            Err(Errors::TooLow {
+                requested: -1,
+                min: 0,
+                max: 5_119_921
+            })
+        );
+        assert_eq!(max17261.VCell().contents(), 65535);
+
+        // 1.5625uV / 10mOhm = 156.25uA per LSB, in uA
+        register!(Current);
+        bitrange_fixed!(Current, current, 15, 0, i16, i32, 625, 4, -5_120_000, 5_119_843);
+
+        assert_eq!(max17261.Current().set_current(-1_000_000), Ok(-1_000_000));
+        assert_eq!(max17261.Current().contents(), -6400i16 as u16);
+        assert_eq!(max17261.Current().get_current(), -1_000_000);
+        assert_eq!(max17261.Current().set_current(-5_120_000), Ok(-5_120_000));
+        assert_eq!(max17261.Current().get_current(), -5_120_000);
+        assert_eq!(
+            max17261.Current().set_current(-5_120_001),
+            Err(Errors::TooLow {
+                requested: -5_120_001,
+                min: -5_120_000,
+                max: 5_119_843
+            })
+        );
+        assert_eq!(max17261.Current().get_current_min(), -5_120_000);
+        assert_eq!(max17261.Current().get_current_max(), 5_119_843);
+
+        // 8 bit fields in milli degrees C
+        register!(MaxMinTemp);
+        bitrange_fixed!(MaxMinTemp, max, 15, 8, i8, i32, 1000, 1, -128_000, 127_000);
+        bitrange_fixed!(MaxMinTemp, min, 7, 0, i8, i32, 1000, 1, -128_000, 127_000);
+
+        assert_eq!(max17261.MaxMinTemp().set_max(45_400), Ok(45_000));
+        assert_eq!(max17261.MaxMinTemp().set_min(-20_500), Ok(-21_000));
+        assert_eq!(max17261.MaxMinTemp().get_max(), 45_000);
+        assert_eq!(max17261.MaxMinTemp().get_min(), -21_000);
+        assert_eq!(max17261.MaxMinTemp().contents(), 0x2DEB);
+    }
+
+    #[test]
+    fn bitrange_offset_test() {
+        use bitterly::{
+            bitrange_fixed, bitrange_quantized, peripheral, register, register_backer, Errors,
+        };
+
+        register_backer!(Register, u8);
+
+        peripheral!(
+            Thermometer,
+            u8,
+            0x48,
+            3,
+            [(Temperature, 0x00, 0), (Limit, 0x01, 1), (Zero, 0x02, 2)]
+        );
+
+        let thermometer = Thermometer::new();
+
+        // value = raw * 0.5 - 40
+        register!(Temperature);
+        bitrange_quantized!(
+            Temperature,
+            temp,
+            7,
+            0,
+            u8,
+            0.5,
+            -40.0,
+            87.5,
+            offset = -40.0
+        );
+
+        assert_eq!(thermometer.Temperature().get_temp_offset(), -40.0);
+        assert_eq!(thermometer.Temperature().get_temp(), -40.0);
+        assert_eq!(thermometer.Temperature().set_temp(25.0), Ok(25.0));
+        assert_eq!(thermometer.Temperature().contents(), 130);
+        assert_eq!(thermometer.Temperature().get_temp(), 25.0);
+        assert_eq!(thermometer.Temperature().set_temp(-40.0), Ok(-40.0));
+        assert_eq!(thermometer.Temperature().set_temp(87.5), Ok(87.5));
+        assert_eq!(thermometer.Temperature().get_temp(), 87.5);
+        assert_eq!(
+            thermometer.Temperature().set_temp(-40.5),
+            Err(Errors::TooLow {
+                requested: -40.5,
+                min: -40.0,
+                max: 87.5
+            })
+        );
+        assert_eq!(
+            thermometer.Temperature().set_temp(88.0),
+            Err(Errors::TooHigh {
+                requested: 88.0,
+                min: -40.0,
+                max: 87.5
+            })
+        );
+        assert_eq!(thermometer.Temperature().get_temp_min(), -40.0);
+        assert_eq!(thermometer.Temperature().get_temp_max(), 87.5);
+
+        // Same field in milli degrees C without floating point
+        register!(Limit);
+        bitrange_fixed!(
+            Limit,
+            temp,
+            7,
+            0,
+            u8,
+            i32,
+            500,
+            1,
+            -40_000,
+            87_500,
+            offset = -40_000
+        );
+
+        assert_eq!(thermometer.Limit().get_temp_offset(), -40_000);
+        assert_eq!(thermometer.Limit().set_temp(25_000), Ok(25_000));
+        assert_eq!(thermometer.Limit().get_temp(), 25_000);
+        assert_eq!(thermometer.Limit().set_temp(-40_000), Ok(-40_000));
+        assert_eq!(thermometer.Limit().get_temp(), -40_000);
+        assert_eq!(
+            thermometer.Limit().set_temp(-40_001),
+            Err(Errors::TooLow {
+                requested: -40_001,
+                min: -40_000,
+                max: 87_500
+            })
+        );
+
+        // Without an offset the value is raw * quantization
+        register!(Zero);
+        bitrange_quantized!(Zero, temp, 7, 0, u8, 0.5, 0.0, 127.5);
+        assert_eq!(thermometer.Zero().get_temp_offset(), 0.0);
+        assert_eq!(thermometer.Zero().set_temp(25.0), Ok(25.0));
+
+        // The limits include the offset and are narrowed to the raw range of the field
+        bitrange_fixed!(
+            Zero,
+            wide,
+            7,
+            0,
+            u8,
+            i32,
+            500,
+            1,
+            -100_000,
+            200_000,
+            offset = -40_000
+        );
+        assert_eq!(thermometer.Zero().get_wide_min(), -40_000);
+        assert_eq!(thermometer.Zero().get_wide_max(), 87_500);
+        assert_eq!(
+            thermometer.Zero().set_wide(90_000),
+            Err(Errors::TooHigh {
+                requested: 90_000,
+                min: -40_000,
+                max: 87_500
+            })
+        );
+    }
+
+    #[test]
+    fn bitrange_sign_extension_test() {
+        use bitterly::{
+            bitrange_fixed, bitrange_quantized, bitrange_raw, peripheral, register,
+            register_backer, Errors,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Sensor,
+            u8,
+            0x36,
+            3,
+            [(Offsets, 0x00, 0), (Trim, 0x01, 1), (Delta, 0x02, 2)]
+        );
+
+        let sensor = Sensor::new();
+
+        // 9 bit and 7 bit two's complement fields packed in one register
+        register!(Offsets);
+        bitrange_raw!(Offsets, high, 15, 7, i16);
+        bitrange_raw!(Offsets, low, 6, 0, i8);
+
+        sensor.Offsets().set_high(-1).unwrap().set_low(-64).unwrap();
+        assert_eq!(sensor.Offsets().contents(), 0xFFC0);
+        assert_eq!(sensor.Offsets().get_high(), -1);
+        assert_eq!(sensor.Offsets().get_low(), -64);
+
+        sensor
+            .Offsets()
+            .set_high(-256)
+            .unwrap()
+            .set_low(63)
+            .unwrap();
+        assert_eq!(sensor.Offsets().contents(), 0x803F);
+        assert_eq!(sensor.Offsets().get_high(), -256);
+        assert_eq!(sensor.Offsets().get_low(), 63);
+
+        sensor.Offsets().set_high(255).unwrap().set_low(0).unwrap();
+        assert_eq!(sensor.Offsets().get_high(), 255);
+        assert_eq!(sensor.Offsets().get_low(), 0);
+
+        // Unsigned fields are not sign extended
+        register!(Trim);
+        bitrange_raw!(Trim, trim, 11, 4, u8);
+        sensor.Trim().set_trim(0xFF).unwrap();
+        assert_eq!(sensor.Trim().get_trim(), 0xFF);
+        assert_eq!(sensor.Trim().contents(), 0x0FF0);
+
+        // Quantized and fixed-point values outside of the field width are rejected
+        register!(Delta);
+        bitrange_quantized!(Delta, delta, 6, 0, i8, 0.5, -32.0, 32.0);
+        bitrange_fixed!(Delta, delta_fixed, 15, 7, i16, i32, 1, 1, -1000, 1000);
+
+        assert_eq!(sensor.Delta().set_delta(-32.0), Ok(-32.0));
+        assert_eq!(sensor.Delta().get_delta(), -32.0);
+        assert_eq!(sensor.Delta().set_delta(31.5), Ok(31.5));
+        assert_eq!(sensor.Delta().get_delta(), 31.5);
+        assert_eq!(
+            sensor.Delta().set_delta(32.0),
+            Err(Errors::TooHigh {
+                requested: 32.0,
+                min: -32.0,
+                max: 31.5
+            })
+        );
+        assert_eq!(sensor.Delta().get_delta(), 31.5);
+
+        assert_eq!(sensor.Delta().set_delta_fixed(-256), Ok(-256));
+        assert_eq!(sensor.Delta().get_delta_fixed(), -256);
+        assert_eq!(sensor.Delta().set_delta_fixed(255), Ok(255));
+        assert_eq!(sensor.Delta().get_delta_fixed(), 255);
+        assert_eq!(
+            sensor.Delta().set_delta_fixed(256),
+            Err(Errors::TooHigh {
+                requested: 256,
+                min: -256,
+                max: 255
+            })
+        );
+        assert_eq!(
+            sensor.Delta().set_delta_fixed(-257),
+            Err(Errors::TooLow {
+                requested: -257,
+                min: -256,
+                max: 255
+            })
+        );
+        assert_eq!(sensor.Delta().get_delta(), 31.5);
+    }
+
+    #[test]
+    fn bitrange_rounding_test() {
+        use bitterly::{
+            bitrange_quantized, peripheral, register, register_backer, Errors, Rounding,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            3,
+            [
+                (MaxMinVolt, 0x1B, 0),
+                (VAlrtTh, 0x01, 1),
+                (TAlrtTh, 0x02, 2)
+            ]
+        );
+
+        let max17261 = Max17261::new();
+
+        register!(MaxMinVolt);
+        bitrange_quantized!(MaxMinVolt, nearest, 15, 8, u8, 0.02, 0.0, 255.0 * 0.02);
+        bitrange_quantized!(
+            MaxMinVolt,
+            exact,
+            7,
+            0,
+            u8,
+            0.02,
+            0.0,
+            255.0 * 0.02,
+            rounding = Exact
+        );
+
+        assert_eq!(
+            max17261.MaxMinVolt().get_nearest_rounding(),
+            Rounding::Nearest
+        );
+        assert_eq!(max17261.MaxMinVolt().set_nearest(3.999), Ok(4.0));
+        assert_eq!(max17261.MaxMinVolt().contents() >> 8, 200);
+        assert_eq!(max17261.MaxMinVolt().set_nearest(3.989), Ok(3.98));
+        assert_eq!(max17261.MaxMinVolt().set_nearest(3.99), Ok(4.0));
+
+        // Values on a step are accepted despite floating point error in the division
+        assert_eq!(max17261.MaxMinVolt().get_exact_rounding(), Rounding::Exact);
+        assert_eq!(max17261.MaxMinVolt().set_exact(3.98), Ok(3.98));
+        assert_eq!(max17261.MaxMinVolt().contents() & 0xFF, 199);
+        assert_eq!(
+            max17261.MaxMinVolt().set_exact(3.999),
+            Err(Errors::QuantizationError)
+        );
+        assert_eq!(max17261.MaxMinVolt().contents() & 0xFF, 199);
+
+        register!(VAlrtTh);
+        bitrange_quantized!(
+            VAlrtTh,
+            vmax,
+            15,
+            8,
+            u8,
+            0.02,
+            0.0,
+            255.0 * 0.02,
+            rounding = Floor
+        );
+        bitrange_quantized!(
+            VAlrtTh,
+            vmin,
+            7,
+            0,
+            i8,
+            0.5,
+            -64.0,
+            63.5,
+            offset = 0.0,
+            rounding = Ceil
+        );
+
+        assert_eq!(max17261.VAlrtTh().set_vmax(3.999), Ok(3.98));
+        assert_eq!(max17261.VAlrtTh().set_vmax(4.0), Ok(4.0));
+        assert_eq!(max17261.VAlrtTh().set_vmin(1.1), Ok(1.5));
+        assert_eq!(max17261.VAlrtTh().set_vmin(-1.1), Ok(-1.0));
+        assert_eq!(max17261.VAlrtTh().set_vmin(-1.0), Ok(-1.0));
+        assert_eq!(
+            max17261.VAlrtTh().set_vmin(63.6),
+            Err(Errors::TooHigh {
+                requested: 63.6,
+                min: -64.0,
+                max: 63.5
+            })
+        );
+
+        // NaN can't be rounded, and infinity is out of range
+        assert_eq!(
+            max17261.VAlrtTh().set_vmax(f32::NAN),
+            Err(Errors::QuantizationError)
+        );
+        assert_eq!(max17261.VAlrtTh().get_vmax(), 4.0);
+        assert_eq!(
+            max17261.VAlrtTh().set_vmax(f32::INFINITY),
+            Err(Errors::TooHigh {
+                requested: f32::INFINITY,
+                min: 0.0,
+                max: 255.0 * 0.02
+            })
+        );
+
+        // Rounding must not step outside of a min / max narrower than the field
+        register!(TAlrtTh);
+        bitrange_quantized!(TAlrtTh, low, 15, 8, u8, 0.02, 1.01, 4.99, rounding = Floor);
+        bitrange_quantized!(TAlrtTh, high, 7, 0, u8, 0.02, 1.01, 4.99, rounding = Ceil);
+
+        assert_eq!(
+            max17261.TAlrtTh().set_low(1.01),
+            Err(Errors::TooLow {
+                requested: 1.01,
+                min: 1.01,
+                max: 4.99
+            })
+        );
+        assert_eq!(max17261.TAlrtTh().set_low(1.03), Ok(1.02));
+        assert_eq!(
+            max17261.TAlrtTh().set_high(4.99),
+            Err(Errors::TooHigh {
+                requested: 4.99,
+                min: 1.01,
+                max: 4.99
+            })
+        );
+        assert_eq!(max17261.TAlrtTh().set_high(4.97), Ok(4.98));
+        assert_eq!(max17261.TAlrtTh().contents(), 51 << 8 | 249);
+    }
+
+    #[test]
+    fn bitrange_errors_test() {
+        use bitterly::{
+            bitrange_fixed, bitrange_quantized, bitrange_raw, peripheral, register,
+            register_backer, Errors, Saturated,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            3,
+            [
+                (ChipRev, 0x00, 0),
+                (MaxMinVolt, 0x1B, 1),
+                (Current, 0x0A, 2)
+            ]
+        );
+
+        let max17261 = Max17261::new();
+
+        register!(ChipRev);
+        bitrange_raw!(ChipRev, rev_h, 7, 4, u8);
+        bitrange_raw!(ChipRev, offset, 15, 8, i16);
+
+        assert_eq!(max17261.ChipRev().get_rev_h_min(), 0);
+        assert_eq!(max17261.ChipRev().get_rev_h_max(), 15);
+        assert_eq!(
+            max17261.ChipRev().set_rev_h(16).err(),
+            Some(Errors::TooHigh {
+                requested: 16,
+                min: 0,
+                max: 15
+            })
+        );
+        assert_eq!(
+            max17261.ChipRev().set_offset(-129).err(),
+            Some(Errors::TooLow {
+                requested: -129,
+                min: -128,
+                max: 127
+            })
+        );
+        assert_eq!(max17261.ChipRev().contents(), 0);
+
+        assert_eq!(
+            max17261.ChipRev().set_rev_h_saturating(20),
+            Saturated {
+                value: 15,
+                clamped: true
+            }
+        );
+        assert_eq!(
+            max17261.ChipRev().set_offset_saturating(-5),
+            Saturated {
+                value: -5,
+                clamped: false
+            }
+        );
+        assert_eq!(max17261.ChipRev().contents(), 0xFBF0);
+
+        register!(MaxMinVolt);
+        bitrange_quantized!(MaxMinVolt, max, 15, 8, u8, 0.02, 0.0, 5.0);
+        bitrange_quantized!(MaxMinVolt, min, 7, 0, u8, 0.02, 0.0, 5.0, rounding = Exact);
+
+        assert_eq!(
+            max17261.MaxMinVolt().set_max(5.1),
+            Err(Errors::TooHigh {
+                requested: 5.1,
+                min: 0.0,
+                max: 5.0
+            })
+        );
+        assert_eq!(
+            max17261.MaxMinVolt().set_max_saturating(5.1),
+            Ok(Saturated {
+                value: 5.0,
+                clamped: true
+            })
+        );
+        assert_eq!(
+            max17261.MaxMinVolt().set_max_saturating(-0.5),
+            Ok(Saturated {
+                value: 0.0,
+                clamped: true
+            })
+        );
+        assert_eq!(
+            max17261.MaxMinVolt().set_max_saturating(4.0),
+            Ok(Saturated {
+                value: 4.0,
+                clamped: false
+            })
+        );
+        assert_eq!(
+            max17261.MaxMinVolt().set_min_saturating(3.71),
+            Err(Errors::QuantizationError)
+        );
+
+        register!(Current);
+        bitrange_fixed!(Current, current, 15, 0, i16, i32, 625, 4, -5_120_000, 5_119_843);
+
+        assert_eq!(
+            max17261.Current().set_current_saturating(6_000_000),
+            Saturated {
+                value: 5_119_844,
+                clamped: true
+            }
+        );
+        assert_eq!(max17261.Current().contents(), i16::MAX as u16);
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn bitrange_quantity_test() {
+        use bitterly::uom::si::electric_current::{ampere, milliampere};
+        use bitterly::uom::si::electric_potential::{millivolt, volt};
+        use bitterly::uom::si::f32::{
+            ElectricCurrent, ElectricPotential, ThermodynamicTemperature,
+        };
+        use bitterly::uom::si::thermodynamic_temperature::{degree_celsius, kelvin};
+        use bitterly::{
+            bitrange_quantity, peripheral, register, register_backer, Errors, Saturated,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            3,
+            [(VCell, 0x09, 0), (Current, 0x0A, 1), (Temp, 0x08, 2)]
+        );
+
+        let max17261 = Max17261::new();
+
+        // Unit conversions aren't exact in f32
+        let close = |a: f32, b: f32| (a - b).abs() <= b.abs() * 1e-5;
+
+        register!(VCell);
+        bitrange_quantity!(
+            VCell,
+            voltage,
+            15,
+            0,
+            u16,
+            ElectricPotential,
+            microvolt,
+            78.125,
+            0.0,
+            78.125 * 65535.0
+        );
+
+        // Any unit can be used to set the value
+        let stored = max17261
+            .VCell()
+            .set_voltage(ElectricPotential::new::<volt>(3.7))
+            .unwrap();
+        assert_eq!(max17261.VCell().contents(), 47360);
+        assert!(close(stored.get::<millivolt>(), 3700.0));
+        assert!(close(max17261.VCell().get_voltage().get::<volt>(), 3.7));
+        assert!(close(max17261.VCell().get_voltage_min().get::<volt>(), 0.0));
+
+        register!(Current);
+        bitrange_quantity!(
+            Current,
+            current,
+            15,
+            0,
+            i16,
+            ElectricCurrent,
+            milliampere,
+            0.15625,
+            -5120.0,
+            0.15625 * 32767.0
+        );
+
+        max17261
+            .Current()
+            .set_current(ElectricCurrent::new::<ampere>(-1.0))
+            .unwrap();
+        assert_eq!(max17261.Current().contents(), -6400i16 as u16);
+        assert!(close(
+            max17261.Current().get_current().get::<milliampere>(),
+            -1000.0
+        ));
+
+        let result = max17261
+            .Current()
+            .set_current(ElectricCurrent::new::<ampere>(6.0));
+        match result {
+            Err(Errors::TooHigh { requested, max, .. }) => {
+                assert!(close(requested.get::<milliampere>(), 6000.0));
+                assert!(close(max.get::<milliampere>(), 0.15625 * 32767.0));
+            }
+            _ => panic!("expected TooHigh, got {:?}", result),
+        }
+
+        register!(Temp);
+        bitrange_quantity!(
+            Temp,
+            temperature,
+            7,
+            0,
+            u8,
+            ThermodynamicTemperature,
+            degree_celsius,
+            0.5,
+            -40.0,
+            87.5,
+            offset = -40.0
+        );
+
+        max17261
+            .Temp()
+            .set_temperature(ThermodynamicTemperature::new::<kelvin>(298.15))
+            .unwrap();
+        assert_eq!(max17261.Temp().contents(), 130);
+        assert!(close(
+            max17261.Temp().get_temperature().get::<degree_celsius>(),
+            25.0
+        ));
+
+        let saturated = max17261
+            .Temp()
+            .set_temperature_saturating(ThermodynamicTemperature::new::<degree_celsius>(100.0))
+            .unwrap();
+        assert!(saturated.clamped);
+        assert!(close(saturated.value.get::<degree_celsius>(), 87.5));
+        assert_eq!(max17261.Temp().contents(), 255);
+        let _: Saturated<ThermodynamicTemperature> = saturated;
+    }
+
+    #[test]
+    fn bitrange_lookup_test() {
+        use bitterly::{
+            bitrange_piecewise, bitrange_table, peripheral, register, register_backer, Errors,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(Max17261, u8, 0x36, 2, [(HibCfg, 0xBA, 0), (Curve, 0xB9, 1)]);
+
+        let max17261 = Max17261::new();
+
+        register!(HibCfg);
+        const HIB_ENTER_TIME: [(u16, f32); 8] = [
+            (0, 5.625),
+            (1, 11.25),
+            (2, 22.5),
+            (3, 45.0),
+            (4, 90.0),
+            (5, 180.0),
+            (6, 360.0),
+            (7, 720.0),
+        ];
+        bitrange_table!(HibCfg, hib_enter_time, 14, 12, u16, HIB_ENTER_TIME);
+        // Only some codes have a value
+        const HIB_EXIT_TIME: &[(u16, f32)] = &[(0, 0.702), (3, 2.808)];
+        bitrange_table!(HibCfg, hib_exit_time, 4, 3, u16, HIB_EXIT_TIME);
+
+        assert_eq!(max17261.HibCfg().get_hib_enter_time(), Some(5.625));
+        assert_eq!(max17261.HibCfg().set_hib_enter_time(45.0), Ok(45.0));
+        assert_eq!(max17261.HibCfg().contents(), 0x3000);
+        // Values between entries use the nearest entry
+        assert_eq!(max17261.HibCfg().set_hib_enter_time(100.0), Ok(90.0));
+        assert_eq!(max17261.HibCfg().set_hib_enter_time(150.0), Ok(180.0));
+        assert_eq!(max17261.HibCfg().get_hib_enter_time(), Some(180.0));
+        assert_eq!(max17261.HibCfg().contents(), 0x5000);
+        assert_eq!(
+            max17261.HibCfg().set_hib_enter_time(721.0),
+            Err(Errors::TooHigh {
+                requested: 721.0,
+                min: 5.625,
+                max: 720.0
+            })
+        );
+        assert_eq!(
+            max17261.HibCfg().set_hib_enter_time(1.0),
+            Err(Errors::TooLow {
+                requested: 1.0,
+                min: 5.625,
+                max: 720.0
+            })
+        );
+        assert_eq!(max17261.HibCfg().contents(), 0x5000);
+
+        assert_eq!(max17261.HibCfg().get_hib_exit_time(), Some(0.702));
+        assert_eq!(max17261.HibCfg().set_hib_exit_time(2.0), Ok(2.808));
+        assert_eq!(max17261.HibCfg().contents(), 0x5018);
+        max17261.HibCfg().update(0x5008);
+        assert_eq!(max17261.HibCfg().get_hib_exit_time(), None);
+
+        // Decreasing curve of temperature against raw ADC value
+        register!(Curve);
+        const THERMISTOR: [(i16, f32); 4] =
+            [(-2000, 100.0), (0, 40.0), (1000, 20.0), (4000, -20.0)];
+        bitrange_piecewise!(Curve, temperature, 15, 0, i16, THERMISTOR);
+
+        assert_eq!(max17261.Curve().get_temperature_min(), -20.0);
+        assert_eq!(max17261.Curve().get_temperature_max(), 100.0);
+        assert_eq!(max17261.Curve().get_temperature(), Some(40.0));
+        assert_eq!(max17261.Curve().set_temperature(25.0), Ok(25.0));
+        assert_eq!(max17261.Curve().contents(), 750);
+        assert_eq!(max17261.Curve().set_temperature(70.0), Ok(70.0));
+        assert_eq!(max17261.Curve().contents(), -1000i16 as u16);
+        assert_eq!(max17261.Curve().set_temperature(0.0), Ok(0.0));
+        assert_eq!(max17261.Curve().contents(), 2500);
+        // Rounded to the nearest raw value
+        assert_eq!(max17261.Curve().set_temperature(39.985), Ok(39.98));
+        assert_eq!(max17261.Curve().contents(), 1);
+        assert_eq!(
+            max17261.Curve().set_temperature(-21.0),
+            Err(Errors::TooLow {
+                requested: -21.0,
+                min: -20.0,
+                max: 100.0
+            })
+        );
+        assert_eq!(
+            max17261.Curve().set_temperature(f32::NAN),
+            Err(Errors::QuantizationError)
+        );
+        assert_eq!(max17261.Curve().contents(), 1);
+
+        max17261.Curve().update(5000);
+        assert_eq!(max17261.Curve().get_temperature(), None);
+    }
+
+    #[test]
+    fn pmbus_test() {
+        use bitterly::pmbus::vout_mode_exponent;
+        use bitterly::{
+            bitrange_direct, bitrange_linear11, bitrange_linear16, bitrange_raw, peripheral,
+            register, register_backer, Errors,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            PowerModule,
+            u8,
+            0x40,
+            4,
+            [
+                (VoutMode, 0x20, 0),
+                (ReadVout, 0x8B, 1),
+                (ReadIout, 0x8C, 2),
+                (ReadTemperature1, 0x8D, 3)
+            ]
+        );
+
+        let module = PowerModule::new();
+
+        register!(ReadIout);
+        bitrange_linear11!(ReadIout, iout, 15, 0);
+
+        assert_eq!(module.ReadIout().set_iout(12.5), Ok(12.5));
+        assert_eq!(module.ReadIout().contents(), 0xD320);
+        assert_eq!(module.ReadIout().get_iout(), 12.5);
+        assert_eq!(module.ReadIout().set_iout(-1.0), Ok(-1.0));
+        assert_eq!(module.ReadIout().contents(), 0xB400);
+        module.ReadIout().update(0x0001);
+        assert_eq!(module.ReadIout().get_iout(), 1.0);
+        assert_eq!(
+            module.ReadIout().set_iout(4.0e7),
+            Err(Errors::TooHigh {
+                requested: 4.0e7,
+                min: -33_554_432.0,
+                max: 33_521_664.0
+            })
+        );
+        assert_eq!(
+            module.ReadIout().set_iout(f32::NAN),
+            Err(Errors::QuantizationError)
+        );
+        assert_eq!(module.ReadIout().contents(), 0x0001);
+
+        register!(VoutMode);
+        bitrange_raw!(VoutMode, mode, 7, 0, u8);
+        register!(ReadVout);
+        bitrange_linear16!(ReadVout, vout, 15, 0);
+
+        module.VoutMode().update(0x17);
+        let exponent = vout_mode_exponent(module.VoutMode().get_mode()).unwrap();
+        assert_eq!(exponent, -9);
+        assert_eq!(vout_mode_exponent(0x40), None);
+
+        assert_eq!(module.ReadVout().set_vout(3.3, exponent), Ok(3.300_781_3));
+        assert_eq!(module.ReadVout().contents(), 1690);
+        assert_eq!(module.ReadVout().get_vout(exponent), 3.300_781_3);
+        assert_eq!(
+            module.ReadVout().set_vout(-0.1, exponent),
+            Err(Errors::TooLow {
+                requested: -0.1,
+                min: 0.0,
+                max: 65535.0 / 512.0
+            })
+        );
+
+        // Y = 4 * X - 100
+        register!(ReadTemperature1);
+        bitrange_direct!(ReadTemperature1, temperature, 15, 0, 4, -100, 0);
+
+        assert_eq!(
+            module.ReadTemperature1().get_temperature_coefficients(),
+            (4, -100, 0)
+        );
+        assert_eq!(module.ReadTemperature1().get_temperature(), 25.0);
+        assert_eq!(module.ReadTemperature1().set_temperature(50.0), Ok(50.0));
+        assert_eq!(module.ReadTemperature1().contents(), 100);
+        assert_eq!(module.ReadTemperature1().set_temperature(0.1), Ok(0.0));
+        assert_eq!(module.ReadTemperature1().contents(), -100i16 as u16);
+        assert_eq!(
+            module.ReadTemperature1().set_temperature(9000.0),
+            Err(Errors::TooHigh {
+                requested: 9000.0,
+                min: -8167.0,
+                max: 8216.75
+            })
+        );
+    }
+
+    #[test]
+    fn bitrange_quantized_derived_range_test() {
+        use bitterly::{bitrange_quantized, peripheral, register, register_backer, Errors};
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            3,
+            [
+                (TAlrtTh, 0x02, 0),
+                (VEmpty, 0x3A, 1),
+                (Temperature, 0x08, 2)
+            ]
+        );
+
+        let max17261 = Max17261::new();
+
+        register!(TAlrtTh);
+        bitrange_quantized!(TAlrtTh, tmax, 15, 8, i8, 1.0);
+        bitrange_quantized!(TAlrtTh, tmin, 7, 0, u8, 0.5);
+
+        assert_eq!(max17261.TAlrtTh().get_tmax_min(), -128.0);
+        assert_eq!(max17261.TAlrtTh().get_tmax_max(), 127.0);
+        assert_eq!(max17261.TAlrtTh().get_tmin_min(), 0.0);
+        assert_eq!(max17261.TAlrtTh().get_tmin_max(), 127.5);
+        assert_eq!(max17261.TAlrtTh().set_tmax(-128.0), Ok(-128.0));
+        assert_eq!(
+            max17261.TAlrtTh().set_tmax(128.0),
+            Err(Errors::TooHigh {
+                requested: 128.0,
+                min: -128.0,
+                max: 127.0
+            })
+        );
+
+        // 9 bit field with a narrower range
+        register!(VEmpty);
+        bitrange_quantized!(VEmpty, ve, 15, 7, u16, 0.01, min = 2.5, max = 5.0);
+        bitrange_quantized!(
+            VEmpty,
+            vr,
+            6,
+            0,
+            u16,
+            0.04,
+            min = 0.0,
+            max = 127.0 * 0.04,
+            rounding = Exact
+        );
+
+        assert_eq!(max17261.VEmpty().get_ve_min(), 2.5);
+        assert_eq!(max17261.VEmpty().get_ve_max(), 5.0);
+        assert_eq!(max17261.VEmpty().set_ve(3.3), Ok(3.3));
+        assert_eq!(max17261.VEmpty().contents() >> 7, 330);
+        assert_eq!(
+            max17261.VEmpty().set_ve(5.05),
+            Err(Errors::TooHigh {
+                requested: 5.05,
+                min: 2.5,
+                max: 5.0
+            })
+        );
+        assert_eq!(
+            max17261.VEmpty().get_vr_rounding(),
+            bitterly::Rounding::Exact
+        );
+        assert_eq!(
+            max17261.VEmpty().set_vr(0.02),
+            Err(Errors::QuantizationError)
+        );
+
+        // The offset is included in the derived range
+        register!(Temperature);
+        bitrange_quantized!(Temperature, temp, 7, 0, u8, 0.5, offset = -40.0);
+
+        assert_eq!(max17261.Temperature().get_temp_min(), -40.0);
+        assert_eq!(max17261.Temperature().get_temp_max(), 87.5);
+        assert_eq!(max17261.Temperature().set_temp(25.0), Ok(25.0));
+        assert_eq!(max17261.Temperature().contents(), 130);
+
+        // A configured limit within half a step of the field is reported narrowed
+        bitrange_quantized!(
+            Temperature,
+            limit,
+            15,
+            8,
+            u8,
+            0.5,
+            offset = -40.0,
+            min = -40.0,
+            max = 87.7
+        );
+
+        assert_eq!(max17261.Temperature().get_limit_min(), -40.0);
+        assert_eq!(max17261.Temperature().get_limit_max(), 87.5);
+        assert_eq!(
+            max17261.Temperature().set_limit(87.6),
+            Err(Errors::TooHigh {
+                requested: 87.6,
+                min: -40.0,
+                max: 87.5
+            })
+        );
+    }
+
+    #[test]
+    fn bitrange_multi_test() {
+        use bitterly::{
+            bitrange_multi_quantized, bitrange_multi_raw, bitrange_raw, peripheral, register,
+            register_backer, Errors,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            5,
+            [
+                (TimerLSB, 0x3E, 0),
+                (TimerHSB, 0xBE, 1),
+                (AdcHigh, 0x10, 2),
+                (AdcMid, 0x11, 3),
+                (AdcLow, 0x12, 4)
+            ]
+        );
+
+        let mut max17261 = Max17261::new();
+
+        register!(TimerLSB);
+        register!(TimerHSB);
+        bitrange_multi_raw!(timer, u32, [(TimerHSB, 15, 0), (TimerLSB, 15, 0)]);
+        bitrange_multi_quantized!(
+            timer_seconds,
+            u32,
+            [(TimerHSB, 15, 0), (TimerLSB, 15, 0)],
+            0.25
+        );
+
+        max17261.set_timer(0x1234_5678).unwrap();
+        assert_eq!(max17261.TimerHSB().contents(), 0x1234);
+        assert_eq!(max17261.TimerLSB().contents(), 0x5678);
+        assert_eq!(max17261.get_timer(), 0x1234_5678);
+        assert_eq!(max17261.get_timer_max(), u32::MAX);
+
+        assert_eq!(max17261.set_timer_seconds(16384.5), Ok(16384.5));
+        assert_eq!(max17261.TimerHSB().contents(), 1);
+        assert_eq!(max17261.TimerLSB().contents(), 2);
+        assert_eq!(max17261.get_timer_seconds_raw(), 65538);
+        assert_eq!(max17261.get_timer_seconds_min(), 0.0);
+        assert!(max17261.set_timer_seconds(-1.0).is_err());
+
+        // Raw values above 2^24 are still quantized to the nearest step
+        bitrange_multi_quantized!(
+            timer_period,
+            u32,
+            [(TimerHSB, 15, 0), (TimerLSB, 15, 0)],
+            0.1758
+        );
+        assert_eq!(max17261.set_timer_period(2_949_494.5), Ok(2_949_494.5));
+        assert_eq!(max17261.get_timer_period_raw(), 16_777_557);
+
+        // A signed 20 bit ADC result in three registers, the lowest nibble of AdcLow is
+        // used for something else
+        register!(AdcHigh);
+        register!(AdcMid);
+        register!(AdcLow);
+        bitrange_raw!(AdcLow, status, 3, 0, u8);
+        bitrange_multi_raw!(adc, i32, [(AdcHigh, 7, 0), (AdcMid, 7, 0), (AdcLow, 7, 4)]);
+
+        max17261.AdcLow().set_status(0xA).unwrap();
+        max17261.set_adc(-2).unwrap();
+        assert_eq!(max17261.AdcHigh().contents(), 0xFF);
+        assert_eq!(max17261.AdcMid().contents(), 0xFF);
+        assert_eq!(max17261.AdcLow().contents(), 0xEA);
+        assert_eq!(max17261.get_adc(), -2);
+        assert_eq!(max17261.AdcLow().get_status(), 0xA);
+
+        max17261.set_adc(0x7FFFF).unwrap();
+        assert_eq!(max17261.get_adc(), 0x7FFFF);
+        assert_eq!(max17261.AdcHigh().contents(), 0x7F);
+        assert_eq!(
+            max17261.set_adc(0x80000).err(),
+            Some(Errors::TooHigh {
+                requested: 0x80000,
+                min: -0x80000,
+                max: 0x7FFFF
+            })
+        );
+        assert_eq!(max17261.get_adc(), 0x7FFFF);
+    }
+
+    #[test]
+    fn register_array_test() {
+        use bitterly::console::{execute, ConsoleError};
+        use bitterly::{
+            bitrange_raw, peripheral, register, register_array, register_backer, Peripheral,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            5,
+            [(Status, 0x00, 0)],
+            arrays: [(QRTable, 0x12, 0x10, 4, 1)],
+            fields: [(QRTable, [qr])]
+        );
+
+        register!(Status);
+        register_array!(QRTable);
+        bitrange_raw!(QRTable, qr, 15, 0, u16);
+
+        let mut max17261 = Max17261::new();
+
+        assert!(max17261.QRTable(4).is_none());
+        let mut qr_table_20 = max17261.QRTable(2).unwrap();
+        assert_eq!(qr_table_20.element(), 2);
+        assert_eq!(qr_table_20.address(), 0x32);
+        assert_eq!(qr_table_20.index(), 3);
+        qr_table_20.set_qr(0x1234).unwrap();
+
+        assert_eq!(max17261.direct_read_by_address(0x32), 0x1234);
+        max17261.direct_update_by_address(0x42, 0x5678);
+        assert_eq!(max17261.QRTable(3).unwrap().get_qr(), 0x5678);
+
+        for (i, mut element) in max17261.QRTable_iter().enumerate() {
+            element.update(i as u16);
+        }
+        let contents: Vec<u16> = max17261.QRTable_iter().map(|r| r.contents()).collect();
+        assert_eq!(contents, [0, 1, 2, 3]);
+        assert_eq!(max17261.Status().contents(), 0);
+
+        assert_eq!(Max17261::QRTable_ARRAY.count, 4);
+        let registers = max17261.registers();
+        assert_eq!(registers.len(), 5);
+        assert_eq!(registers[4].name, "QRTable");
+        assert_eq!(registers[4].element, Some(3));
+        assert_eq!(registers[4].to_string(), "QRTable[3]");
+        assert_eq!(registers[4].address, 0x42);
+        assert_eq!(registers[0].element, None);
+        assert_eq!(max17261.fields(2), &[QRTable::qr]);
+        assert!(max17261.fields(0).is_empty());
+
+        // The console names each element of the array
+        let mut out = String::new();
+        execute(&mut max17261, "list", &mut out).unwrap();
+        assert_eq!(
+            out,
+            "Status @ 0x00\nQRTable[0] @ 0x12\nQRTable[1] @ 0x22\nQRTable[2] @ 0x32\nQRTable[3] @ 0x42\n"
+        );
+
+        out.clear();
+        execute(&mut max17261, "write QRTable[1] 0x1234", &mut out).unwrap();
+        execute(&mut max17261, "get qrtable[0x1].qr", &mut out).unwrap();
+        assert_eq!(out, "QRTable[1] @ 0x22 = 0x1234\nQRTable[1].qr = 4660\n");
+        assert_eq!(max17261.QRTable(1).unwrap().contents(), 0x1234);
+
+        // Reading the array prints every element, and the rest need the element
+        out.clear();
+        execute(&mut max17261, "read QRTable", &mut out).unwrap();
+        assert_eq!(
+            out,
+            "QRTable[0] @ 0x12 = 0x0000\nQRTable[1] @ 0x22 = 0x1234\nQRTable[2] @ 0x32 = 0x0002\nQRTable[3] @ 0x42 = 0x0003\n"
+        );
+        assert_eq!(
+            execute(&mut max17261, "write QRTable 0", &mut out),
+            Err(ConsoleError::MissingArgument)
+        );
+        assert_eq!(
+            execute(&mut max17261, "read QRTable[4]", &mut out),
+            Err(ConsoleError::UnknownRegister)
+        );
+
+        out.clear();
+        execute(&mut max17261, "dump", &mut out).unwrap();
+        assert!(out.ends_with("QRTable[3] @ 0x42 = 0x0003\n"));
+    }
+
+    #[test]
+    fn bitfield_array_test() {
+        use bitterly::{bitfield_array, bitrange_array, peripheral, register, register_backer};
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Expander,
+            u8,
+            0x20,
+            2,
+            [(IntMask, 0x00, 0), (GpioMode, 0x01, 1)]
+        );
+
+        register!(IntMask);
+        bitfield_array!(IntMask, ch_en, 0, 8, 1);
+        bitfield_array!(IntMask, ch_flag, 8, 4, 2);
+
+        register!(GpioMode);
+        bitrange_array!(GpioMode, mode, 0, 2, 8, 2, u8);
+        bitrange_array!(GpioMode, trim, 0, 4, 4, 4, i8);
+
+        let expander = Expander::new();
+
+        let mut int_mask = expander.IntMask();
+        int_mask.set_ch_en(0, true).set_ch_en(7, true);
+        int_mask.set_ch_flag(1, true).set_ch_flag(3, true);
+        assert_eq!(int_mask.contents(), 0b0100_0100_1000_0001);
+        assert!(int_mask.get_ch_en(7));
+        assert!(!int_mask.get_ch_en(6));
+        let flags: Vec<bool> = int_mask.ch_flag_iter().collect();
+        assert_eq!(flags, [false, true, false, true]);
+        assert_eq!(IntMask::CH_EN_COUNT, 8);
+        // Each element is described on its own, without the bits between elements
+        assert_eq!(IntMask::ch_flag[3].msb, 14);
+        assert_eq!(IntMask::ch_flag[3].mask(), 1 << 14);
+        assert_eq!(IntMask::ch_flag[1].lsb, 10);
+
+        let mut gpio_mode = expander.GpioMode();
+        gpio_mode.set_mode(0, 0b01).unwrap();
+        gpio_mode.set_mode(7, 0b11).unwrap();
+        assert_eq!(gpio_mode.contents(), 0xC001);
+        assert_eq!(gpio_mode.get_mode(7), 0b11);
+        assert!(gpio_mode.set_mode(1, 0b100).is_err());
+        assert_eq!(gpio_mode.contents(), 0xC001);
+
+        // The same bits as 4 bit signed groups
+        assert_eq!(gpio_mode.get_trim(3), -4);
+        gpio_mode.set_trim(1, -1).unwrap();
+        let trims: Vec<i8> = gpio_mode.trim_iter().collect();
+        assert_eq!(trims, [1, -1, 0, -4]);
+        assert_eq!(gpio_mode.get_trim_min(), -8);
+        assert_eq!(GpioMode::mode[7].mask(), 0xC000);
+        assert_eq!(GpioMode::trim[1].mask(), 0x00F0);
+        assert!(GpioMode::trim[1].signed);
+    }
+
+    #[test]
+    #[should_panic]
+    fn bitfield_array_out_of_range_test() {
+        use bitterly::{bitfield_array, peripheral, register, register_backer};
+
+        register_backer!(Register, u8);
+        peripheral!(Expander, u8, 0x20, 1, [(IntMask, 0x00, 0)]);
+        register!(IntMask);
+        bitfield_array!(IntMask, ch_en, 0, 8, 1);
+
+        Expander::new().IntMask().get_ch_en(8);
+    }
+
+    #[test]
+    fn bitrange_scattered_test() {
+        use bitterly::console::execute;
+        use bitterly::{
+            bitrange_enum_values, bitrange_scattered, bitrange_scattered_quantized,
+            bitrange_scattered_raw, peripheral, register, register_backer, Errors,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Sensor,
+            u8,
+            0x48,
+            1,
+            [(Config, 0x01, 0)],
+            fields: [(Config, [threshold, trim])]
+        );
+
+        register!(Config);
+        bitrange_scattered_raw!(Config, threshold, [(15, 12), (3, 0)], u8);
+        bitrange_scattered_raw!(Config, trim, [(15, 12), (3, 0)], i8);
+        bitrange_scattered_raw!(Config, small, [(14, 12), (2, 0)], u8);
+        bitrange_scattered_quantized!(Config, limit, [(15, 12), (3, 0)], u8, 0.5, offset = 20.0);
+
+        bitrange_enum_values!(
+            Mode,
+            u16,
+            [(Off, 0), (Single, 1), (Burst, 5), (Continuous, 7)]
+        );
+        bitrange_scattered!(Config, mode, [(11, 11), (5, 4)], Mode);
+
+        let mut sensor = Sensor::new();
+        let mut config = sensor.Config();
+
+        config.update(0x0FF0);
+        config.set_threshold(0xA5).unwrap();
+        assert_eq!(config.contents(), 0xAFF5);
+        assert_eq!(config.get_threshold(), 0xA5);
+        assert_eq!(config.get_trim(), -91);
+        assert_eq!(Config::threshold.msb, 15);
+        assert_eq!(Config::threshold.lsb, 0);
+
+        config.set_trim(-1).unwrap();
+        assert_eq!(config.contents(), 0xFFFF);
+        assert_eq!(
+            config.set_small(64).err(),
+            Some(Errors::TooHigh {
+                requested: 64,
+                min: 0,
+                max: 63
+            })
+        );
+
+        config.clear();
+        assert_eq!(config.set_limit(30.5), Ok(30.5));
+        assert_eq!(config.get_limit_raw(), 21);
+        assert_eq!(config.contents(), 0x1005);
+        assert_eq!(config.get_limit_max(), 20.0 + 255.0 * 0.5);
+        assert!(matches!(config.set_limit(19.0), Err(Errors::TooLow { .. })));
+
+        config.clear();
+        config.set_mode(Mode::Burst);
+        assert_eq!(config.contents(), 0x0810);
+        assert_eq!(config.get_mode(), Some(Mode::Burst));
+        config.update(0x0820);
+        assert_eq!(config.get_mode(), None);
+
+        // The console only touches the bits of the slices
+        assert_eq!(Config::threshold.mask(), 0xF00F);
+        assert_eq!(Config::threshold.width(), 8);
+        sensor.Config().update(0x0FF0);
+        let mut out = String::new();
+        execute(&mut sensor, "list Config", &mut out).unwrap();
+        execute(&mut sensor, "set Config.threshold 0xA5", &mut out).unwrap();
+        execute(&mut sensor, "get Config.trim", &mut out).unwrap();
+        assert_eq!(
+            out,
+            "Config.threshold [15:12,3:0]\nConfig.trim [15:12,3:0]\nConfig.threshold = 165\nConfig.trim = -91\n"
+        );
+        assert_eq!(sensor.Config().contents(), 0xAFF5);
+    }
+
+    /// Bus shared by the bus_read / bus_write tests. Reads return the first bytes of
+    /// `data`, and every transfer is logged as `('r' | 'w', address, bytes)`.
+    struct MockBus {
+        data: Vec<u8>,
+        transfers: Vec<(char, usize, Vec<u8>)>,
+    }
+
+    impl MockBus {
+        fn new(data: &[u8]) -> Self {
+            MockBus {
+                data: data.to_vec(),
+                transfers: Vec::new(),
+            }
+        }
+
+        /// Address and bytes of each write
+        fn written(&self) -> Vec<(usize, Vec<u8>)> {
+            self.transfers
+                .iter()
+                .filter(|t| t.0 == 'w')
+                .map(|t| (t.1, t.2.clone()))
+                .collect()
+        }
+    }
+
+    impl bitterly::bus::RegisterBus for MockBus {
+        type Error = ();
+
+        fn read(&mut self, _device: u16, address: usize, bytes: &mut [u8]) -> Result<(), ()> {
+            bytes.copy_from_slice(&self.data[..bytes.len()]);
+            self.transfers.push(('r', address, bytes.to_vec()));
+            Ok(())
+        }
+
+        fn write(&mut self, _device: u16, address: usize, bytes: &[u8]) -> Result<(), ()> {
+            self.transfers.push(('w', address, bytes.to_vec()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn paged_peripheral_test() {
+        use bitterly::console::execute;
+        use bitterly::{bitrange_raw, peripheral, register, register_backer};
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Phy,
+            u8,
+            0x01,
+            4,
+            [
+                (Control, 0x00, 0),
+                (Page, 0x1F, 1),
+                (LedControl, 0x10, 2, page = 3),
+                (WolControl, 0x10, 3, page = 7)
+            ],
+            page_select: Page
+        );
+
+        register!(Control);
+        register!(Page);
+        register!(LedControl);
+        register!(WolControl);
+        bitrange_raw!(LedControl, mode, 3, 0, u8);
+
+        let mut phy = Phy::new();
+        let mut bus = MockBus::new(&[0x03, 0x10]);
+
+        assert_eq!(Phy::page_of(RegisterIndex::LedControl as usize), Some(3));
+        assert_eq!(phy.WolControl().address(), 0x10);
+
+        // The page is selected before the first paged access, and only when it changes
+        assert_eq!(
+            phy.bus_read(&mut bus, RegisterIndex::LedControl as usize),
+            Ok(0x0310)
+        );
+        assert_eq!(
+            phy.bus_read(&mut bus, RegisterIndex::Control as usize),
+            Ok(0x0310)
+        );
+        phy.LedControl().set_mode(5).unwrap();
+        phy.bus_write(&mut bus, RegisterIndex::LedControl as usize)
+            .unwrap();
+        assert_eq!(
+            phy.bus_read(&mut bus, RegisterIndex::WolControl as usize),
+            Ok(0x0310)
+        );
+        assert_eq!(
+            bus.transfers,
+            [
+                ('w', 0x1F, vec![0x00, 0x03]),
+                ('r', 0x10, vec![0x03, 0x10]),
+                ('r', 0x00, vec![0x03, 0x10]),
+                ('w', 0x10, vec![0x03, 0x15]),
+                ('w', 0x1F, vec![0x00, 0x07]),
+                ('r', 0x10, vec![0x03, 0x10])
+            ]
+        );
+        assert_eq!(phy.get_device_page(), Some(7));
+        assert_eq!(phy.Page().contents(), 7);
+
+        // Address lookups follow the page in the page select register
+        assert_eq!(phy.find_index_by_address(0x10), Some(3));
+        phy.Page().update(3);
+        assert_eq!(phy.find_index_by_address(0x10), Some(2));
+        assert_eq!(phy.direct_read_by_address(0x10), 0x0315);
+
+        // Writing the page select register directly is tracked too
+        bus.transfers.clear();
+        phy.bus_write(&mut bus, RegisterIndex::Page as usize)
+            .unwrap();
+        phy.bus_read(&mut bus, RegisterIndex::LedControl as usize)
+            .unwrap();
+        assert_eq!(
+            bus.transfers,
+            [('w', 0x1F, vec![0x00, 0x03]), ('r', 0x10, vec![0x03, 0x10])]
+        );
+
+        phy.invalidate_page();
+        bus.transfers.clear();
+        phy.bus_read(&mut bus, RegisterIndex::LedControl as usize)
+            .unwrap();
+        assert_eq!(
+            bus.transfers,
+            [('w', 0x1F, vec![0x00, 0x03]), ('r', 0x10, vec![0x03, 0x10])]
+        );
+
+        let mut out = String::new();
+        execute(&mut phy, "list", &mut out).unwrap();
+        assert_eq!(
+            out,
+            "Control @ 0x00\nPage @ 0x1F\nLedControl @ 0x10 page 3\nWolControl @ 0x10 page 7\n"
+        );
+    }
+
+    #[test]
+    fn mixed_width_test() {
+        use bitterly::console::execute;
+        use bitterly::{bitfield, bitrange_raw, peripheral, register, register_backer, Peripheral};
+
+        // 8 bit config register and 24 bit conversion results, stored in u32
+        register_backer!(Register, u32);
+
+        peripheral!(
+            Adc,
+            u8,
+            0x48,
+            3,
+            [
+                (Config, 0x00, 0, width = 8),
+                (Data, 0x01, 1, width = 24),
+                (Offset, 0x02, 2)
+            ]
+        );
+
+        register!(Config, u8);
+        bitfield!(Config, start, 7);
+        register!(Data);
+        bitrange_raw!(Data, result, 23, 0, i32);
+        register!(Offset);
+
+        let mut adc = Adc::new();
+        let mut bus = MockBus::new(&[0xFF; 4]);
+
+        assert_eq!(Config::WIDTH, 8);
+        assert_eq!(Data::WIDTH, 24);
+        assert_eq!(Offset::WIDTH, 32);
+
+        let mut config = adc.Config();
+        config.update(0x81);
+        let contents: u8 = config.contents();
+        assert_eq!(contents, 0x81);
+        assert!(config.get_start());
+
+        // Values are masked to the width of the register
+        adc.direct_update_by_index(1, 0xFF80_0000);
+        assert_eq!(adc.Data().contents(), 0x80_0000);
+        assert_eq!(adc.Data().get_result(), -0x80_0000);
+
+        assert_eq!(adc.bus_read(&mut bus, 1), Ok(0xFF_FFFF));
+        assert_eq!(adc.bus_read(&mut bus, 0), Ok(0xFF));
+        adc.bus_write(&mut bus, 2).unwrap();
+        let lengths: Vec<(usize, usize)> = bus.transfers.iter().map(|t| (t.1, t.2.len())).collect();
+        assert_eq!(lengths, [(0x01, 3), (0x00, 1), (0x02, 4)]);
+
+        let widths: Vec<u32> = adc.registers().iter().map(|r| r.width).collect();
+        assert_eq!(widths, [8, 24, 32]);
+
+        let mut out = String::new();
+        execute(&mut adc, "read Config", &mut out).unwrap();
+        assert_eq!(out, "Config @ 0x00 = 0xFF\n");
+        assert!(execute(&mut adc, "write Config 0x100", &mut out).is_err());
+    }
+
+    #[test]
+    fn byte_order_test() {
+        use bitterly::{peripheral, register, register_backer, ByteError, ByteOrder};
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Max17261,
+            u8,
+            0x36,
+            4,
+            [
+                (Status, 0x00, 0),
+                (VAlrtTh, 0x01, 1),
+                (Config, 0x02, 2, width = 8),
+                (DevName, 0x03, 3, byte_order = BigEndian)
+            ],
+            byte_order: LittleEndian
+        );
+
+        register!(Status);
+        register!(VAlrtTh);
+        register!(Config, u8);
+        register!(DevName);
+
+        let mut max17261 = Max17261::new();
+        let mut bus = MockBus::new(&[0x34, 0x12]);
+
+        assert_eq!(Max17261::BYTE_ORDER, ByteOrder::LittleEndian);
+        assert_eq!(Max17261::byte_order_of(3), ByteOrder::BigEndian);
+
+        max17261.Status().update(0x1234);
+        assert_eq!(max17261.Status().to_bytes(), [0x34, 0x12]);
+        max17261.DevName().from_bytes([0x40, 0x61]);
+        assert_eq!(max17261.DevName().contents(), 0x4061);
+        assert_eq!(max17261.Config().to_bytes(), [0x00]);
+
+        max17261
+            .load_bytes(0x01, &[0xFF, 0x00, 0xA5, 0x40, 0x62])
+            .unwrap();
+        assert_eq!(max17261.VAlrtTh().contents(), 0x00FF);
+        assert_eq!(max17261.Config().contents(), 0xA5);
+        assert_eq!(max17261.DevName().contents(), 0x4062);
+
+        let mut bytes = [0; 7];
+        max17261.store_bytes(0x00, &mut bytes).unwrap();
+        assert_eq!(bytes, [0x34, 0x12, 0xFF, 0x00, 0xA5, 0x40, 0x62]);
+
+        assert_eq!(
+            max17261.load_bytes(0x03, &[0x00, 0x00, 0x00]).err(),
+            Some(ByteError::UnknownAddress(0x04))
+        );
+        assert_eq!(
+            max17261.store_bytes(0x00, &mut [0; 3]),
+            Err(ByteError::Length)
+        );
+
+        // With byte addressing a 16 bit register takes two addresses
+        peripheral!(
+            Eeprom: Register,
+            u8,
+            0x50,
+            3,
+            [(Id, 0x00, 0), (Mode, 0x02, 1, width = 8), (Limit, 0x03, 2)],
+            byte_order: LittleEndian,
+            addressing: Byte
+        );
+        let mut eeprom = Eeprom::new();
+        eeprom
+            .load_bytes(0x00, &[0x34, 0x12, 0x05, 0xCD, 0xAB])
+            .unwrap();
+        assert_eq!(eeprom.direct_read_by_address(0x00), 0x1234);
+        assert_eq!(eeprom.direct_read_by_address(0x02), 0x05);
+        assert_eq!(eeprom.direct_read_by_address(0x03), 0xABCD);
+        let mut bytes = [0; 3];
+        eeprom.store_bytes(0x02, &mut bytes).unwrap();
+        assert_eq!(bytes, [0x05, 0xCD, 0xAB]);
+        assert_eq!(
+            eeprom.load_bytes(0x00, &[0; 4]).err(),
+            Some(ByteError::Length)
+        );
+
+        assert_eq!(max17261.bus_read(&mut bus, 0), Ok(0x1234));
+        assert_eq!(max17261.bus_read(&mut bus, 3), Ok(0x3412));
+        max17261.bus_write(&mut bus, 0).unwrap();
+        max17261.bus_write(&mut bus, 3).unwrap();
+        assert_eq!(
+            bus.written(),
+            [(0x00, vec![0x34, 0x12]), (0x03, vec![0x34, 0x12])]
+        );
+    }
+
+    #[test]
+    fn wide_register_backer_test() {
+        use bitterly::{bitfield, bitrange_raw, peripheral, register, register_backer};
+
+        register_backer!(Register, u128);
+
+        let mut reg = Register::new(0);
+        reg.set_bit(127);
+        assert!(reg.is_set(127));
+        assert_eq!(reg.contents(), 1 << 127);
+        assert_eq!(reg.mask(BitRange::new(0, 127)), u128::MAX);
+        assert_eq!(reg.mask(BitRange::new(64, 127)), u128::MAX << 64);
+        reg.set_range(BitRange::new(120, 127), 0xA5);
+        assert_eq!(reg.get_range(BitRange::new(120, 127)), 0xA5);
+        reg.set_all();
+        assert_eq!(reg.contents(), u128::MAX);
+
+        peripheral!(Timer, u8, 0x10, 1, [(Counter, 0x00, 0)]);
+
+        register!(Counter);
+        bitfield!(Counter, overflow, 127);
+        bitrange_raw!(Counter, high, 127, 64, i64);
+        bitrange_raw!(Counter, low, 63, 0, u64);
+
+        let timer = Timer::new();
+        let mut counter = timer.Counter();
+        counter.set_low(u64::MAX).unwrap();
+        counter.set_high(-1).unwrap();
+        assert_eq!(counter.contents(), u128::MAX);
+        assert!(counter.get_overflow());
+        counter.set_overflow(false);
+        assert_eq!(counter.get_high(), i64::MAX);
+        assert_eq!(counter.get_low(), u64::MAX);
+    }
+
+    #[test]
+    fn u64_register_backer_test() {
+        use bitterly::register_backer;
+
+        register_backer!(Register, u64);
+
+        let mut reg = Register::new(0);
+        reg.set_bit(63).set_bit(0);
+        assert_eq!(reg.contents(), 0x8000_0000_0000_0001);
+        assert_eq!(reg.mask(BitRange::new(32, 63)), 0xFFFF_FFFF_0000_0000);
+        reg.toggle_bit(63);
+        assert!(reg.is_clear(63));
+    }
+
+    #[test]
+    fn narrow_register_backer_test() {
+        use bitterly::{bitrange_raw, peripheral, register, register_backer, Peripheral};
+
+        // 24 bit registers stored in a u32
+        register_backer!(Register, u32, 24);
+
+        let mut reg = Register::new(0xFFFF_FFFF);
+        assert_eq!(reg.contents(), 0xFF_FFFF);
+        reg.clear_all().set_all();
+        assert_eq!(reg.contents(), 0xFF_FFFF);
+        reg.update(0x8000_0001);
+        assert_eq!(reg.contents(), 0x01);
+        assert_eq!(reg.mask(BitRange::new(16, 23)), 0xFF_0000);
+
+        peripheral!(Adc, u8, 0x48, 1, [(Data, 0x00, 0)]);
+
+        register!(Data);
+        bitrange_raw!(Data, result, 23, 0, i32);
+
+        let mut adc = Adc::new();
+        assert_eq!(Data::WIDTH, 24);
+        assert_eq!(adc.registers()[0].width, 24);
+        adc.Data().set_result(-1).unwrap();
+        assert_eq!(adc.Data().contents(), 0xFF_FFFF);
+        adc.direct_update_by_index(0, 0xFFFF_FFFE);
+        assert_eq!(adc.Data().get_result(), -2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn narrow_register_backer_bit_test() {
+        use bitterly::register_backer;
+
+        register_backer!(Register, u32, 24);
+
+        Register::new(0).set_bit(24);
+    }
+
+    #[test]
+    fn multiple_peripherals_test() {
+        use bitterly::{
+            bitfield, bitrange_multi_raw, bitrange_raw, peripheral, register, register_array,
+            register_backer, Peripheral,
+        };
+
+        register_backer!(Reg8: u8);
+        register_backer!(Reg16: u16);
+
+        // Both devices have a Status register at address 0x00
+        peripheral!(Charger: Reg8, u8, 0x6B, 2, [(Status, 0x00, 0), (Control, 0x01, 1)]);
+        peripheral!(Gauge: Reg16, u8, 0x36, 5, [(Status, 0x00, 0), (TimerHSB, 0xBE, 1)],
+            arrays: [(QRTable, 0x12, 0x10, 3, 2)]);
+
+        register!(Charger::Status);
+        register!(Charger::Control as ChargerControl);
+        register!(Gauge::Status);
+        register!(Gauge::TimerHSB);
+        register_array!(Gauge::QRTable);
+
+        bitfield!(ChargerStatus, charging, 0);
+        bitrange_raw!(ChargerControl, current, 7, 4, u8);
+        bitfield!(GaugeStatus, por, 1);
+        bitrange_raw!(GaugeQRTable, qr, 15, 0, u16);
+        bitrange_multi_raw!(Gauge: timer, u32, [(TimerHSB, 15, 0), (Status, 15, 0)]);
+
+        let charger = Charger::new();
+        let mut gauge = Gauge::new();
+
+        charger.Status().set_charging(true);
+        charger.Control().set_current(0xA).unwrap();
+        gauge.Status().set_por(true);
+        assert_eq!(charger.Status().contents(), 0x01);
+        assert_eq!(charger.Control().contents(), 0xA0);
+        assert_eq!(gauge.Status().contents(), 0x0002);
+
+        assert_eq!(Charger::Control_ADDRESS, 0x01);
+        assert_eq!(Gauge::TimerHSB_INDEX, 1);
+        assert_eq!(Charger::Status_WIDTH, 8);
+        assert_eq!(Gauge::Status_WIDTH, 16);
+
+        gauge.QRTable(2).unwrap().set_qr(0x1234).unwrap();
+        assert_eq!(gauge.direct_read_by_address(0x32), 0x1234);
+
+        gauge.set_timer(0x0001_8000).unwrap();
+        assert_eq!(gauge.TimerHSB().contents(), 0x0001);
+        assert_eq!(gauge.get_timer(), 0x0001_8000);
+
+        assert_eq!(charger.registers().len(), 2);
+        assert_eq!(gauge.registers().len(), 5);
+        assert_eq!(gauge.read_register(0), 0x8000);
+    }
+
+    #[test]
+    fn peripheral_variants_test() {
+        use bitterly::{
+            bitfield, bitrange_multi_raw, bitrange_raw, peripheral, register, register_backer,
+            Family, Peripheral,
+        };
+
+        register_backer!(Reg16: u16);
+
+        peripheral!(Max17261: Reg16, u8, 0x36, 6, [
+            (Status, 0x00, 0),
+            (AtRate, 0x04, 1, only = [Max17260]),
+            (Config, 0x1D, 2, only = [Max17261, Max17263]),
+            // The Max17260 lays out Config differently
+            (Config2, 0x1D, 3, only = [Max17260]),
+            (TimerHSB, 0xBE, 4),
+            (TimerLSB, 0x3E, 5)
+        ], variants: [Max17260, Max17263]);
+
+        register!(Max17261::Status);
+        register!(Max17261::AtRate);
+        register!(Max17261::Config);
+        register!(Max17261::Config2 as Max17260Config, overrides: [Max17260::Config]);
+        register!(Max17261::TimerHSB);
+        register!(Max17261::TimerLSB);
+
+        bitfield!(Max17261Status, por, 1);
+        bitfield!(Max17261Config, ten, 2);
+        bitrange_raw!(Max17260Config, mode, 3, 0, u8);
+        bitrange_multi_raw!(Max17261: timer, u32, [(TimerHSB, 15, 0), (TimerLSB, 15, 0)]);
+
+        // Generic code works on every member of the family
+        fn power_on<P: Family<Base = Max17261>>(device: &mut P) -> u32 {
+            device.Status().set_por(true);
+            device.set_timer(0x0001_0002).unwrap();
+            device.get_timer()
+        }
+
+        let mut max17261 = Max17261::new();
+        let mut max17260 = Max17260::new();
+        let mut max17263 = Max17263::new();
+        assert_eq!(power_on(&mut max17261), 0x0001_0002);
+        assert_eq!(power_on(&mut max17260), 0x0001_0002);
+        assert_eq!(power_on(&mut max17263), 0x0001_0002);
+        assert!(max17260.Status().get_por());
+
+        // The Max17260 has its own layout behind the same accessor
+        max17261.Config().set_ten(true);
+        max17260.Config().set_mode(0x5).unwrap();
+        assert_eq!(max17260.Config2().get_mode(), 0x5);
+        max17260.AtRate().update(0x1234);
+        assert_eq!(max17261.direct_read_by_address(0x1D), 0x0004);
+        assert_eq!(max17260.direct_read_by_address(0x1D), 0x0005);
+        assert_eq!(max17260.direct_read_by_address(0x04), 0x1234);
+        assert_eq!(max17263.find_index_by_address(0x04), None);
+
+        assert_eq!(max17261.registers().len(), 4);
+        assert_eq!(max17260.registers().len(), 5);
+        assert!(max17260.registers().iter().all(|r| r.name != "Config"));
+        assert!(!<Max17263 as Family>::PRESENT[Max17261::AtRate_INDEX]);
+    }
+
+    #[test]
+    fn register_overlay_test() {
+        use bitterly::{
+            bitfield, bitrange_quantized, bitrange_raw, peripheral, register, register_backer,
+            register_overlay,
+        };
+
+        register_backer!(Register, u16);
+
+        peripheral!(
+            Monitor,
+            u8,
+            0x40,
+            2,
+            [(Config, 0x00, 0), (Threshold, 0x01, 1)],
+            overlays: [
+                (VoltageThreshold, Threshold),
+                (CurrentThreshold, Threshold),
+                (FastConfig, Config)
+            ]
+        );
+
+        register!(Config);
+        bitfield!(Config, current_mode, 15);
+        bitfield!(Config, fast, 0);
+
+        // Threshold holds a voltage or a current depending on Config
+        register!(Threshold);
+        register_overlay!(Threshold as VoltageThreshold, |device| !device
+            .Config()
+            .get_current_mode());
+        register_overlay!(
+            Threshold as CurrentThreshold,
+            |device| device.Config().get_current_mode(),
+            u16
+        );
+        bitrange_quantized!(VoltageThreshold, volts, 11, 0, u16, 0.5);
+        bitrange_raw!(CurrentThreshold, milliamps, 14, 0, u16);
+        bitfield!(CurrentThreshold, sign, 15);
+
+        // A layout selected by a field of the same register
+        register_overlay!(Config as FastConfig, |device| device.Config().get_fast());
+        bitrange_raw!(FastConfig, divider, 7, 4, u8);
+
+        let monitor = Monitor::new();
+        monitor.VoltageThreshold().unwrap().set_volts(10.0).unwrap();
+        assert!(monitor.CurrentThreshold().is_none());
+        assert_eq!(monitor.Threshold().contents(), 20);
+
+        monitor.Config().set_current_mode(true);
+        assert!(monitor.VoltageThreshold().is_none());
+        let mut current = monitor.CurrentThreshold().unwrap();
+        assert_eq!(current.get_milliamps(), 20);
+        current.set_milliamps(1000).unwrap();
+        current.set_sign(true);
+        assert_eq!(monitor.Threshold().contents(), 0x83E8);
+        assert_eq!(CurrentThreshold::WIDTH, 16);
+
+        assert!(monitor.FastConfig().is_none());
+        monitor.Config().set_fast(true);
+        monitor.FastConfig().unwrap().set_divider(0x3).unwrap();
+        assert_eq!(monitor.Config().contents(), 0x8031);
+    }
+
+    #[test]
+    fn memory_mapped_peripheral_test() {
+        use bitterly::{bitfield, bitrange_raw, peripheral, register, register_backer};
+
+        register_backer!(Reg32: u32);
+
+        peripheral!(Uart: Reg32, u32, 0x00, 3,
+            [(Data, 0x00, 0), (Status, 0x04, 1), (Control, 0x08, 2)],
+            storage: MemoryMapped);
+
+        register!(Uart::Data as Data);
+        register!(Uart::Status as Status, access: [overrun, framing_error]);
+        register!(Uart::Control as Control);
+        bitfield!(Status, rx_ready, 5);
+        bitfield!(Status, overrun, 3, W1C);
+        bitfield!(Status, framing_error, 4, W1C);
+        bitfield!(Status, irq_enable, 8);
+        bitfield!(Control, enable, 0);
+        bitrange_raw!(Control, baud, 15, 8, u8);
+
+        // Registers of the device, here a buffer on the host
+        let mut memory = [0u32; 3];
+        let base = memory.as_mut_ptr();
+        let mut uart = unsafe { Uart::new(base as *mut u8) };
+
+        uart.Control().set_enable(true).set_baud(0x2A).unwrap();
+        assert_eq!(unsafe { base.add(2).read_volatile() }, 0x2A01);
+
+        // The device sets a status bit and receives a byte
+        unsafe {
+            base.add(1).write_volatile(0x20);
+            base.write_volatile(0x55);
+        }
+        assert!(uart.Status().get_rx_ready());
+        assert_eq!(uart.Data().contents(), 0x55);
+        assert_eq!(uart.direct_read_by_address(0x04), 0x20);
+
+        uart.direct_update_by_address(0x00, 0xAA);
+        assert_eq!(unsafe { base.read_volatile() }, 0xAA);
+
+        // W1C flags are written as 0 unless acknowledged, so that they stay set on the device
+        unsafe { base.add(1).write_volatile(0x38) };
+        uart.Status().set_irq_enable(true);
+        assert_eq!(unsafe { base.add(1).read_volatile() }, 0x120);
+        unsafe { base.add(1).write_volatile(0x138) };
+        uart.Status().acknowledge(Status::overrun);
+        assert_eq!(unsafe { base.add(1).read_volatile() }, 0x128);
+        uart.direct_update_by_address(0x04, 0x118);
+        assert_eq!(unsafe { base.add(1).read_volatile() }, 0x100);
+    }
+
+    #[test]
+    fn memory_mapped_width_test() {
+        use bitterly::{bitfield, bitrange_raw, peripheral, register, register_backer};
+
+        register_backer!(Reg32: u32);
+
+        // Byte and half word registers next to each other in a 32 bit backer
+        peripheral!(Timer: Reg32, u32, 0x00, 4, [
+            (Ctrl, 0x00, 0, width = 8),
+            (Stat, 0x01, 1, width = 8),
+            (Prescale, 0x02, 2, width = 16),
+            (Count, 0x04, 3)
+        ], storage: MemoryMapped);
+
+        register!(Timer::Ctrl as Ctrl);
+        register!(Timer::Stat as Stat, access: [done]);
+        register!(Timer::Prescale as Prescale);
+        register!(Timer::Count as Count);
+        bitfield!(Stat, done, 0, W1C);
+        bitrange_raw!(Prescale, divider, 11, 0, u16);
+
+        let mut memory = [0u32; 2];
+        let base = memory.as_mut_ptr() as *mut u8;
+        let timer = unsafe { Timer::new(base) };
+        let byte = |offset: usize| unsafe { base.add(offset).read_volatile() };
+
+        // Each register is written alone, leaving its neighbours as they are
+        unsafe { base.add(1).write_volatile(0xA1) };
+        timer.Ctrl().update(0x05);
+        assert_eq!((byte(0), byte(1)), (0x05, 0xA1));
+        assert_eq!(timer.Stat().contents(), 0xA1);
+
+        timer.Prescale().set_divider(0x123).unwrap();
+        assert_eq!(timer.Prescale().contents(), 0x123);
+        assert_eq!((byte(0), byte(1)), (0x05, 0xA1));
+
+        // Stat writes its W1C flag as 0 unless acknowledged, and only its own byte
+        timer.Stat().update(0xF1);
+        assert_eq!(byte(1), 0xF0);
+        timer.Stat().acknowledge(Stat::done);
+        assert_eq!((byte(0), byte(1)), (0x05, 0xF1));
+
+        timer.Count().update(0xDEAD_BEEF);
+        assert_eq!(timer.Count().contents(), 0xDEAD_BEEF);
+        assert_eq!(timer.Prescale().contents(), 0x123);
+        assert_eq!(timer.Ctrl().contents(), 0x05);
+        assert_eq!(timer.direct_read_by_address(0x01), 0xF1);
+    }
+
+    #[test]
+    fn register_alias_test() {
+        use bitterly::{bitfield, bitrange_raw, peripheral, register, register_backer, Peripheral};
+
+        register_backer!(Register, u8);
+
+        // Rev B of the datasheet renamed Control to BasicControl
+        peripheral!(Phy, u8, 0x20, 2, [(Control, 0x00, 0), (Status, 0x01, 1)],
+            aliases: [(BasicControl, Control), (LinkStatus, Status)]);
+
+        register!(Control);
+        register!(BasicControl);
+        register!(Status);
+        register!(LinkStatus);
+        bitfield!(Control, reset, 7);
+        bitrange_raw!(BasicControl, speed, 1, 0, u8);
+        bitfield!(LinkStatus, link_up, 2);
+
+        let phy = Phy::new();
+        phy.Control().set_reset(true);
+        phy.BasicControl().set_speed(0x2).unwrap();
+        assert_eq!(phy.Control().contents(), 0x82);
+        assert_eq!(phy.BasicControl().contents(), 0x82);
+        assert_eq!(phy.BasicControl().address(), 0x00);
+
+        phy.Status().update(0x04);
+        assert!(phy.LinkStatus().get_link_up());
+
+        assert_eq!(RegisterAddress::LinkStatus, 0x01);
+        assert_eq!(RegisterIndex::BasicControl, RegisterIndex::Control);
+        assert_eq!(Phy::LinkStatus_INDEX, 1);
+        // Aliases aren't separate registers
+        assert_eq!(phy.registers().len(), 2);
+
+        register_backer!(Reg16: u16);
+
+        peripheral!(Max17261: Reg16, u8, 0x36, 2, [(RepCap, 0x05, 0), (FullCapRep, 0x10, 1)],
+            aliases: [(RemCap, RepCap)]);
+
+        register!(Max17261::RepCap);
+        register!(Max17261::RemCap);
+
+        let max17261 = Max17261::new();
+        max17261.RemCap().update(0x1234);
+        assert_eq!(max17261.RepCap().contents(), 0x1234);
+        assert_eq!(
+            max17261.direct_read_by_address(Max17261::RemCap_ADDRESS),
+            0x1234
+        );
+    }
+
+    #[test]
+    fn field_access_test() {
+        use bitterly::{
+            bitfield, bitrange_raw, peripheral, register, register_backer, register_overlay,
+            Access, Peripheral, RegisterFields,
+        };
+
+        register_backer!(Register, u8);
+
+        peripheral!(
+            Max14748,
+            u8,
+            0x48,
+            3,
+            [
+                (DevInt1, 0x06, 0),
+                (Status, 0x08, 1),
+                (Control, 0x0A, 2)
+            ],
+            aliases: [(IntStatus, Status)],
+            overlays: [(TestControl, Control)],
+            fields: [
+                (Status, [Enable]),
+                (IntStatus, [Pending]),
+                (Control, [Reset]),
+                (TestControl, [Trigger])
+            ]
+        );
+
+        register!(DevInt1, access: [ChgInt, ThmInt]);
+        bitfield!(DevInt1, ChgInt, 3, RC);
+        bitfield!(DevInt1, ThmInt, 4, RC);
+
+        register!(Status, access: [Fault, Ready, Alarm]);
+        bitfield!(Status, Fault, 0, W1C);
+        bitfield!(Status, Ready, 1, W0C);
+        bitfield!(Status, Alarm, 2, RS);
+        bitfield!(Status, Enable, 7);
+
+        // An alias declares a group of W1C flags of the same register, with bits given
+        // in any form
+        register!(IntStatus, access: [Pending]);
+        bitrange_raw!(IntStatus, Pending, 6, 0x4, u8, W1C);
+
+        register!(Control, access: [Reset]);
+        bitfield!(Control, Reset, 0, ReadWrite);
+
+        // Control in test mode, whose Trigger bit is write 1 to clear
+        register_overlay!(
+            Control as TestControl,
+            |device| device.Status().get_Enable(),
+            access: [Trigger]
+        );
+        bitfield!(TestControl, Trigger, 1, W1C);
+
+        assert_eq!(DevInt1::ChgInt.access, Access::RC);
+        assert_eq!(Status::Fault.access, Access::W1C);
+        assert_eq!(Status::Enable.access, Access::ReadWrite);
+        assert_eq!(IntStatus::Pending.access, Access::W1C);
+        assert_eq!(Status::Alarm.mask(), 0b100);
+        assert_eq!(Status::W1C_MASK, 0b1);
+        assert_eq!(Status::W0C_MASK, 0b10);
+        assert_eq!(Max14748::WRITE_MASKS[1], (0b111_0001, 0b10));
+        assert_eq!(Max14748::WRITE_MASKS[2], (0b10, 0));
+
+        assert!(<Status as RegisterFields>::has_field(&Status::Fault));
+        assert!(!<Status as RegisterFields>::has_field(&Status::Enable));
+        assert!(!<Status as RegisterFields>::has_field(&DevInt1::ChgInt));
+        assert!(<IntStatus as RegisterFields>::has_field(
+            &IntStatus::Pending
+        ));
+
+        let mut max14748 = Max14748::new();
+        let mut bus = MockBus::new(&[0xFF]);
+
+        // The fields of a register, its alias and its overlay are listed together
+        assert_eq!(max14748.fields(1), [Status::Enable, IntStatus::Pending]);
+        assert_eq!(max14748.fields(2), [Control::Reset, TestControl::Trigger]);
+        assert!(max14748.fields(0).is_empty());
+
+        max14748.bus_read(&mut bus, 1).unwrap();
+        assert_eq!(max14748.write_value(1), 0b1000_1110);
+        max14748.Status().set_Enable(false);
+        max14748.bus_write(&mut bus, 1).unwrap();
+        assert_eq!(bus.written(), [(0x08, vec![0b0000_1110])]);
+
+        bus.transfers.clear();
+        max14748
+            .acknowledge(&mut bus, &max14748.Status(), Status::Fault)
+            .unwrap();
+        assert!(!max14748.Status().get_Fault());
+        assert_eq!(bus.written(), [(0x08, vec![0b0000_1111])]);
+
+        bus.transfers.clear();
+        max14748
+            .acknowledge(&mut bus, &max14748.Status(), Status::Ready)
+            .unwrap();
+        assert!(!max14748.Status().get_Ready());
+        assert_eq!(bus.written(), [(0x08, vec![0b0000_1100])]);
+
+        bus.transfers.clear();
+        max14748
+            .acknowledge(&mut bus, &max14748.IntStatus(), IntStatus::Pending)
+            .unwrap();
+        assert_eq!(max14748.IntStatus().get_Pending(), 0);
+        assert_eq!(bus.written(), [(0x08, vec![0b0111_1110])]);
+
+        bus.transfers.clear();
+        max14748.Status().set_Alarm(false);
+        max14748
+            .acknowledge(&mut bus, &max14748.Status(), Status::Alarm)
+            .unwrap();
+        assert!(max14748.Status().get_Alarm());
+        max14748.bus_read(&mut bus, 0).unwrap();
+        max14748
+            .acknowledge(&mut bus, &max14748.DevInt1(), DevInt1::ChgInt)
+            .unwrap();
+        assert!(!max14748.DevInt1().get_ChgInt());
+        assert!(max14748.DevInt1().get_ThmInt());
+        assert!(bus.written().is_empty());
+
+        max14748.Control().set_Reset(true);
+        max14748
+            .acknowledge(&mut bus, &max14748.Control(), Control::Reset)
+            .unwrap();
+        assert!(!max14748.Control().get_Reset());
+        assert_eq!(bus.written(), [(0x0A, vec![0x00])]);
+
+        // The overlay's W1C bit is masked when writing the register it overlays
+        max14748.Control().update(0b10);
+        assert_eq!(max14748.write_value(2), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn acknowledge_field_of_another_register_test() {
+        use bitterly::{bitfield, peripheral, register, register_backer};
+
+        register_backer!(Register, u8);
+
+        peripheral!(
+            Max14748,
+            u8,
+            0x48,
+            2,
+            [(DevInt1, 0x06, 0), (Status, 0x08, 1)]
+        );
+
+        register!(DevInt1, access: [ChgInt]);
+        bitfield!(DevInt1, ChgInt, 3, RC);
+
+        register!(Status, access: [Fault]);
+        bitfield!(Status, Fault, 0, W1C);
+
+        let mut max14748 = Max14748::new();
+        let mut bus = MockBus::new(&[0xFF]);
+        let _ = max14748.acknowledge(&mut bus, &max14748.DevInt1(), Status::Fault);
+    }
+
+    // A peripheral declared in another module than the code using it
+    mod gauge {
+        use bitterly::{bitfield, peripheral, register, register_array, register_backer};
+
+        register_backer!(Reg16: u16);
+
+        peripheral!(Max17261: Reg16, u8, 0x36, 4, [
+            (Status, 0x00, 0),
+            (AtRate, 0x04, 1, only = [Max17260])
+        ], variants: [Max17260], arrays: [(QRTable, 0x12, 0x10, 2, 2)]);
+
+        register!(Max17261::Status);
+        register!(Max17261::AtRate);
+        register_array!(Max17261::QRTable);
+        bitfield!(Max17261Status, por, 1);
+    }
+
+    #[test]
+    fn inherent_accessor_test() {
+        // The accessors of the base peripheral don't need the Access traits
+        use gauge::{Max17261, Max17261Status_por};
+
+        let max17261 = Max17261::new();
+        max17261.Status().set_por(true);
+        assert!(max17261.Status().get_por());
+        assert_eq!(max17261.QRTable_iter().count(), 2);
+        assert!(max17261.QRTable(2).is_none());
+    }
 }